@@ -0,0 +1,82 @@
+//! Compares naive per-pair glob matching with the precompiled [`PatternMatcher`].
+//!
+//! Run with `cargo bench -p fast_staged --bench matching`.
+
+use fast_glob::glob_match;
+use fast_staged::matcher::PatternMatcher;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 20;
+
+fn patterns() -> Vec<String> {
+  let extensions = [
+    "rs", "ts", "tsx", "js", "jsx", "css", "scss", "md", "json", "toml", "yaml", "py", "go", "rb",
+    "java", "kt", "swift", "c", "h", "cpp",
+  ];
+
+  extensions
+    .iter()
+    .flat_map(|ext| [format!("**/*.{}", ext), format!("src/**/*.{}", ext)])
+    .collect()
+}
+
+fn files() -> Vec<String> {
+  let extensions = ["rs", "ts", "md", "png", "lock", "svg"];
+
+  (0..10_000)
+    .map(|i| {
+      format!(
+        "packages/pkg{}/src/module{}/file{}.{}",
+        i % 50,
+        i % 7,
+        i,
+        extensions[i % 6]
+      )
+    })
+    .collect()
+}
+
+fn measure(name: &str, run: impl Fn() -> usize) -> Duration {
+  let started = Instant::now();
+  let mut matched = 0;
+
+  for _ in 0..ITERATIONS {
+    matched = black_box(run());
+  }
+
+  let elapsed = started.elapsed() / ITERATIONS;
+  println!(
+    "{:<12} {:>8.2}ms per run, {} files matched",
+    name,
+    elapsed.as_secs_f64() * 1000.0,
+    matched
+  );
+
+  elapsed
+}
+
+fn main() {
+  let patterns = patterns();
+  let files = files();
+
+  let naive = measure("naive", || {
+    files
+      .iter()
+      .filter(|file| patterns.iter().any(|pattern| glob_match(pattern, file)))
+      .count()
+  });
+
+  let matcher = PatternMatcher::new(patterns.iter().map(String::as_str));
+  let compiled = measure("precompiled", || {
+    files
+      .iter()
+      .filter(|file| matcher.first_match(file).is_some())
+      .count()
+  });
+
+  println!(
+    "speedup: {:.1}x",
+    naive.as_secs_f64() / compiled.as_secs_f64()
+  );
+}
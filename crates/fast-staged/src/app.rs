@@ -1,17 +1,48 @@
-use crate::config::Config;
+use crate::cache::{ResumeState, RunCache};
+use crate::cli::Cli;
+use crate::command::{CommandStatus, status_rollup};
+use crate::config::{
+  OnEmpty, OnMissingCommand, RenamePaths, SymbolsConfig, WaitOnExit, duplicate_patterns,
+};
 use crate::event::{AppEvent, Event, EventHandler};
-use crate::file::FileCommand;
-use crate::model::StateModel;
-use crate::render::render_frame;
-use crate::task::TaskPool;
-use crossterm::event::Event::Key;
+use crate::file::{FileCommand, FilePatterns, MatchedCommands};
+use crate::keymap::{Action, action_for};
+use crate::lock::RunLock;
+use crate::model::{QueueStats, StateModel};
+use crate::profile::Profile;
+use crate::provider::{ConfigDiscovery, ConfigProvider, FileProvider, GitIndex, WorkingTree};
+use crate::render::{dry_run_table, print_summary, render_frame};
+use crate::report::{
+  DEFAULT_ANNOTATION_PATTERN, LineStream, ProgressStream, RepoReport, RunReport, RunSummary,
+  github_annotations, html_report, in_github_actions,
+};
+use crate::snapshot::FileSnapshot;
+use crate::task::{RUNNING_ENV, TaskPool};
+use crossterm::event::Event::{Key, Resize};
+use crossterm::event::KeyEvent;
 use crossterm::event::KeyEventKind;
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::DefaultTerminal;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::IsTerminal;
 use std::path::PathBuf;
+use std::sync::Arc;
 use thiserror::Error;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, watch};
 use tokio::time::{Duration, Instant};
 
+/// How long the final frame stays on screen in `timed` mode, unless `linger_ms` is set.
+const DEFAULT_LINGER: Duration = Duration::from_millis(500);
+
+/// Redraw interval while nothing changes: keeps the spinner and elapsed times moving.
+const FALLBACK_TICK: Duration = Duration::from_millis(100);
+
+/// Minimum pause between frames woken by task changes, unless `redraw_interval_ms` is set.
+const DEFAULT_REDRAW_INTERVAL: Duration = Duration::from_millis(30);
+
+/// How many tasks the "Slowest" panel shows.
+const SLOWEST_TASKS_LIMIT: usize = 5;
+
 #[derive(Debug, Error)]
 pub enum AppError {
   #[error("Configuration file not found. Checked paths: {checked_paths:?}")]
@@ -20,6 +51,9 @@ pub enum AppError {
   #[error("Invalid configuration in {path:?}: {details}")]
   ConfigInvalid { path: PathBuf, details: String },
 
+  #[error("Config already exists: {path:?}. Use --force to overwrite it.")]
+  ConfigExists { path: PathBuf },
+
   #[error("Not a git repository. Current directory: {dir:?}")]
   NotGitRepository { dir: PathBuf },
 
@@ -29,6 +63,55 @@ pub enum AppError {
   #[error("No files matched any patterns. Patterns checked: {patterns:?}")]
   NoFilesMatched { patterns: Vec<String> },
 
+  #[error("Tasks failed in groups: {}", groups.join(", "))]
+  TasksFailed { groups: Vec<String> },
+
+  #[error("Runs failed in repositories: {}", repos.join(", "))]
+  ReposFailed { repos: Vec<String> },
+
+  #[error("--repo is given several times; only the fast-staged CLI runs several repositories")]
+  SeveralRepos,
+
+  #[error("No commands to run for the staged files")]
+  NothingToRun,
+
+  #[error(
+    "fast-staged was started by one of its own commands (FAST_STAGED_RUNNING is set). A command that runs `git commit` or fast-staged would loop forever."
+  )]
+  RecursiveInvocation,
+
+  #[error("Another fast-staged run holds {path} in this repository. Wait for it to finish.")]
+  AlreadyRunning { path: String },
+
+  #[error("Staged files not covered by any pattern: {files:?}")]
+  UncoveredFiles { files: Vec<String> },
+
+  #[error(
+    "Command '{command}' for {file} is a chain step and also runs on its own or in another chain. Chain steps are never deduplicated; remove one of them."
+  )]
+  DuplicateChainStep { file: String, command: String },
+
+  #[error("Patterns defined in several groups:\n{details}")]
+  DuplicatePatterns { details: String },
+
+  #[error("Invalid warning_pattern in group '{group}': {details}")]
+  InvalidWarningPattern { group: String, details: String },
+
+  #[error("Invalid fail_on_output in group '{group}': {details}")]
+  InvalidFailOnOutput { group: String, details: String },
+
+  #[error("Invalid filter_output in group '{group}': {details}")]
+  InvalidFilterOutput { group: String, details: String },
+
+  #[error("Invalid capture in group '{group}': {details}")]
+  InvalidCapturePattern { group: String, details: String },
+
+  #[error("Invalid annotation_pattern: {details}")]
+  InvalidAnnotationPattern { details: String },
+
+  #[error("Unknown command alias '@{alias}' in group '{group}'")]
+  UnknownCommandAlias { alias: String, group: String },
+
   #[error("Failed to execute command '{command}': {reason}")]
   CommandNotFound { command: String, reason: String },
 
@@ -55,19 +138,67 @@ pub type Result<T> = std::result::Result<T, AppError>;
 
 #[derive(Debug)]
 pub struct App {
+  pub cli: Cli,
   // Event stream.
   pub events: EventHandler,
   pub model: StateModel,
   pub task_pool: TaskPool,
   pub start_time: Option<Instant>,
+  // Корень репозитория (или текущий каталог без него), от него считаются все пути
+  pub root: PathBuf,
   pub changed_files: Vec<String>,
+  pub snapshot: FileSnapshot,
+  pub wait_on_exit: Option<WaitOnExit>,
+  // Сколько держать итоговый кадр в режиме `timed`
+  pub linger: Duration,
+  pub finished_at: Option<Instant>,
+  // Время последнего кадра и минимальная пауза до следующего
+  pub last_draw: Option<Instant>,
+  pub redraw_interval: Duration,
+  // Разбор вывода команд в аннотации GitHub Actions
+  pub annotation_pattern: Option<Regex>,
+  // (группа, паттерн) без совпадений, выводятся с `--unused-patterns`
+  pub unused_patterns: Vec<(String, String)>,
+  // Куда записать HTML-отчет после выполнения
+  pub html_report: Option<PathBuf>,
+  // Сколько упавших задач прощается каждой группе (`max_failures`)
+  pub max_failures: HashMap<String, usize>,
+  // Что делать с командами, которых нет в PATH
+  pub on_missing_command: OnMissingCommand,
+  // Время по фазам для `--profile`
+  pub profile: Profile,
+  // Предупреждения конфига, выводятся после восстановления терминала
+  pub warnings: Vec<String>,
+  // Без TUI и вывода в stdout, результат возвращается вызывающему коду
+  pub headless: bool,
+  // Откуда берутся файлы и конфиг, в тестах подменяются
+  pub file_provider: Box<dyn FileProvider>,
+  pub config_provider: Box<dyn ConfigProvider>,
 }
 
 impl Default for App {
   fn default() -> Self {
     Self {
+      cli: Cli::default(),
       start_time: None,
+      root: PathBuf::new(),
       changed_files: Vec::new(),
+      snapshot: FileSnapshot::default(),
+      wait_on_exit: None,
+      linger: DEFAULT_LINGER,
+      finished_at: None,
+      last_draw: None,
+      redraw_interval: DEFAULT_REDRAW_INTERVAL,
+      annotation_pattern: None,
+      on_missing_command: OnMissingCommand::default(),
+      html_report: None,
+      max_failures: HashMap::new(),
+      unused_patterns: Vec::new(),
+      profile: Profile::default(),
+      warnings: Vec::new(),
+      headless: false,
+      file_provider: Box::new(GitIndex),
+      config_provider: Box::new(ConfigDiscovery),
       events: EventHandler::new(),
       model: StateModel::default(),
       task_pool: TaskPool::new(),
@@ -77,28 +208,363 @@ impl Default for App {
 
 impl App {
   /// Construct a new instance of [`App`].
-  pub fn new() -> Self {
-    Self::default()
+  pub fn new(cli: Cli) -> Self {
+    Self {
+      file_provider: file_provider(&cli),
+      cli,
+      ..Self::default()
+    }
+  }
+
+  /// Construct an [`App`] that never touches the terminal, for embedding.
+  pub fn headless(cli: Cli) -> Self {
+    Self {
+      file_provider: file_provider(&cli),
+      cli,
+      start_time: None,
+      root: PathBuf::new(),
+      changed_files: Vec::new(),
+      snapshot: FileSnapshot::default(),
+      wait_on_exit: None,
+      linger: DEFAULT_LINGER,
+      finished_at: None,
+      last_draw: None,
+      redraw_interval: DEFAULT_REDRAW_INTERVAL,
+      annotation_pattern: None,
+      on_missing_command: OnMissingCommand::default(),
+      html_report: None,
+      max_failures: HashMap::new(),
+      unused_patterns: Vec::new(),
+      profile: Profile::default(),
+      warnings: Vec::new(),
+      headless: true,
+      config_provider: Box::new(ConfigDiscovery),
+      events: EventHandler::without_terminal(),
+      model: StateModel::default(),
+      task_pool: TaskPool::new(),
+    }
+  }
+
+  /// Replaces where the files and the config come from, e.g. with fixed ones in tests.
+  pub fn with_providers(
+    mut self,
+    file_provider: Box<dyn FileProvider>,
+    config_provider: Box<dyn ConfigProvider>,
+  ) -> Self {
+    self.file_provider = file_provider;
+    self.config_provider = config_provider;
+    self
   }
 
   /// Run the application's main loop.
+  ///
+  /// Fails when a group has more failed tasks than its `max_failures`.
   pub async fn run(mut self) -> color_eyre::Result<()> {
+    self.finish().await?;
+
+    Ok(self.check_failures().await?)
+  }
+
+  /// Runs one repository of a `--repo` fan-out without the TUI.
+  ///
+  /// Fails like [`App::run`] when a group has more failed tasks than its
+  /// `max_failures`, but the results of the tasks are kept either way.
+  pub async fn run_repo(mut self) -> RepoReport {
+    let repo = self
+      .cli
+      .repo
+      .first()
+      .map(|repo| repo.display().to_string())
+      .unwrap_or_default();
+    let result = self.execute().await;
+    let tasks = self.task_pool.report().await;
+
+    let error = match result {
+      Ok(_) => self.check_failures().await.err().map(|err| err.to_string()),
+      Err(err) => Some(err.to_string()),
+    };
+
+    RepoReport { repo, tasks, error }
+  }
+
+  /// Fails when a group has more failed tasks than its `max_failures`.
+  async fn check_failures(&self) -> Result<()> {
+    let statuses = self.task_pool.statuses().await;
+    let mut failures: BTreeMap<&str, usize> = BTreeMap::new();
+
+    for (task, status) in self.task_pool.states.iter().zip(&statuses) {
+      if status.is_failure() {
+        *failures.entry(task.group_name.as_str()).or_default() += 1;
+      }
+    }
+
+    let mut failed_groups = Vec::new();
+
+    for (group, count) in failures {
+      let allowed = self.max_failures.get(group).copied().unwrap_or(0);
+
+      if count > allowed {
+        failed_groups.push(group.to_string());
+      } else if !self.headless {
+        eprintln!(
+          "Group '{}': {} failure(s) tolerated by max_failures = {}",
+          group, count, allowed
+        );
+      }
+    }
+
+    if !failed_groups.is_empty() {
+      return Err(AppError::TasksFailed {
+        groups: failed_groups,
+      });
+    }
+
+    Ok(())
+  }
+
+  /// Runs with the TUI like [`App::run`] and returns the results of all tasks
+  /// and their totals as well.
+  pub async fn run_reporting(mut self) -> color_eyre::Result<RunReport> {
+    self.finish().await
+  }
+
+  /// Runs the tasks, prints the summary and collects the results.
+  async fn finish(&mut self) -> color_eyre::Result<RunReport> {
+    let executed = self.execute().await?;
+
+    for warning in &self.warnings {
+      eprintln!("Warning: {}", warning);
+    }
+
+    if self.cli.unused_patterns && !self.unused_patterns.is_empty() {
+      eprintln!("Patterns that matched no staged file:");
+
+      for (group, pattern) in &self.unused_patterns {
+        eprintln!("  [{}] {}", group, pattern);
+      }
+    }
+
+    if !executed {
+      return Ok(RunReport {
+        tasks: Vec::new(),
+        summary: RunSummary::default(),
+      });
+    }
+
+    if self.cli.ndjson {
+      // Итог уже выведен в поток событий
+    } else if self.cli.json {
+      let report = self.task_pool.report().await;
+      println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+      print_summary(&self.model);
+    }
+
+    if in_github_actions() {
+      let pattern = match self.annotation_pattern.take() {
+        Some(pattern) => pattern,
+        None => Regex::new(DEFAULT_ANNOTATION_PATTERN)?,
+      };
+
+      for annotation in github_annotations(&self.task_pool, &pattern).await {
+        // Раннер разбирает команды и из stderr, а stdout в JSON-режимах занят отчетом
+        if self.cli.json || self.cli.ndjson {
+          eprintln!("{}", annotation);
+        } else {
+          println!("{}", annotation);
+        }
+      }
+    }
+
+    // В stderr, чтобы не ломать JSON в stdout
+    if self.cli.profile {
+      eprintln!("Profile:");
+
+      for line in self.profile.lines() {
+        eprintln!("{}", line);
+      }
+    }
+
+    Ok(self.run_report().await)
+  }
+
+  /// Runs all tasks without a TUI and returns their results instead of printing them.
+  pub async fn run_headless(mut self) -> color_eyre::Result<RunReport> {
+    self.execute().await?;
+
+    Ok(self.run_report().await)
+  }
+
+  /// Results of all tasks and their totals, with the wall time since the start.
+  async fn run_report(&self) -> RunReport {
+    let wall_ms = self
+      .start_time
+      .map_or(0, |start_time| start_time.elapsed().as_millis());
+
+    RunReport {
+      tasks: self.task_pool.report().await,
+      summary: self.task_pool.summary(wall_ms).await,
+    }
+  }
+
+  /// Runs the tasks to completion; returns `false` if the run was skipped.
+  async fn execute(&mut self) -> color_eyre::Result<bool> {
+    // Команда, которая снова вызывает git commit, запустила бы хук по кругу
+    if std::env::var_os(RUNNING_ENV).is_some() {
+      return Err(AppError::RecursiveInvocation.into());
+    }
+
     self.model.running = true;
     self.start_time = Some(Instant::now());
-    self.changed_files = FileCommand::get_changed_files().await?;
 
-    let mut terminal = ratatui::init();
+    // Файлы не из индекса считаются от текущего каталога, репозиторий не нужен
+    let in_repo = self.file_provider.is_git_index();
+
+    // Все пути дальше считаются от корня репозитория, даже при запуске из подкаталога.
+    // Текущий каталог процесса не меняется: при встраивании он принадлежит хосту
+    self.root = if in_repo {
+      // Несколько репозиториев запускает только `fanout`, по одному `App` на каждый
+      if self.cli.repo.len() > 1 {
+        return Err(AppError::SeveralRepos.into());
+      }
+
+      let start_dir = match self.cli.repo.first() {
+        Some(path) => path.clone(),
+        None => std::env::current_dir()?,
+      };
+      FileCommand::repo_root(&start_dir)?
+    } else {
+      std::env::current_dir()?
+    };
+
+    // Держим блокировку до конца выполнения, её снимает drop.
+    // Берется в корне репозитория, чтобы запуски в разных репозиториях не мешали друг другу
+    let _lock = RunLock::acquire(&self.root)?;
 
-    let config = Config::load()?;
+    if in_repo
+      && let Some(operation) = FileCommand::operation_in_progress(&self.root).await?
+      && !self.cli.force
+    {
+      if !self.headless {
+        eprintln!(
+          "Skipping: git {} is in progress. Use --force to run anyway.",
+          operation
+        );
+      }
+      return Ok(false);
+    }
 
-    let file_commands = FileCommand::match_files_to_commands(&config, &self.changed_files)?;
+    // В режиме NDJSON терминал не захватывается, stdout отдается под поток событий
+    let interactive =
+      !self.cli.ndjson && !self.cli.dry_run && !self.headless && std::io::stdout().is_terminal();
+    let mut terminal = interactive.then(ratatui::init);
+
+    let prepared = self.prepare(&mut terminal).await;
+
+    // До запуска задач TUI закрывается при любом раннем выходе
+    let mut file_commands = match prepared {
+      Ok((_, file_commands)) if !file_commands.is_empty() => file_commands,
+      Ok((on_empty, _)) => {
+        if terminal.is_some() {
+          ratatui::restore();
+        }
 
-    self.task_pool.execute_commands(file_commands).await?;
+        match on_empty {
+          OnEmpty::Silent => {}
+          OnEmpty::Message if !self.headless => eprintln!("No commands to run for staged files."),
+          OnEmpty::Message => {}
+          OnEmpty::Error => return Err(AppError::NothingToRun.into()),
+        }
+
+        return Ok(false);
+      }
+      Err(err) => {
+        if terminal.is_some() {
+          ratatui::restore();
+        }
+
+        return Err(err);
+      }
+    };
+
+    // `--dry-run` только показывает, что запустилось бы, TUI для него не открывается
+    if self.cli.dry_run {
+      if !self.headless {
+        print!("{}", dry_run_table(&file_commands));
+      }
+
+      return Ok(false);
+    }
+
+    let mut progress = (self.cli.ndjson && !self.headless).then(ProgressStream::default);
+    // Без терминала (CI, pipe) выводим построчный лог с префиксами
+    let mut lines = (terminal.is_none() && !self.cli.ndjson && !self.cli.json && !self.headless)
+      .then(|| LineStream::new(self.model.symbols.clone()));
+
+    // Запоминаем содержимое файлов команд, чтобы после выполнения понять, что изменилось.
+    // Из индекса берутся готовые id блобов, с диска читаются только измененные файлы
+    let phase = Instant::now();
+    let targets: Vec<String> = file_commands
+      .iter()
+      .flat_map(|file_cmd| {
+        if file_cmd.files.is_empty() {
+          vec![file_cmd.filename.clone()]
+        } else {
+          file_cmd.files.to_vec()
+        }
+      })
+      .collect::<BTreeSet<_>>()
+      .into_iter()
+      .collect();
+    self.snapshot = if self.file_provider.is_git_index() {
+      FileSnapshot::from_index(&self.root, &targets).await?
+    } else {
+      FileSnapshot::capture(&self.root, &targets).await?
+    };
+    self.profile.record("snapshot", phase.elapsed());
+
+    let mut cache = self.cli.since_last_run.then(|| RunCache::load(&self.root));
+    // Без `--resume` состояние прерванного запуска отбрасывается и пишется заново
+    let mut resume = if self.cli.resume {
+      ResumeState::load(&self.root)
+    } else {
+      ResumeState::default()
+    };
+
+    for file_cmd in &mut file_commands {
+      let Some(hash) = self.snapshot.hash(&file_cmd.filename) else {
+        continue;
+      };
+
+      let cached = cache
+        .as_ref()
+        .is_some_and(|cache| cache.is_fresh(&file_cmd.filename, &file_cmd.command, &hash));
+      let resumed =
+        self.cli.resume && resume.is_fresh(&file_cmd.filename, &file_cmd.command, &hash);
+
+      file_cmd.cached = cached || resumed;
+    }
+
+    let execute_start = Instant::now();
+    let missing = self
+      .task_pool
+      .execute_commands(&self.root, file_commands, self.on_missing_command)
+      .await?;
+
+    if self.on_missing_command == OnMissingCommand::Warn {
+      for command in missing {
+        self
+          .warnings
+          .push(format!("Command not found in PATH, skipped: {}", command));
+      }
+    }
 
     let (render_tx, mut render_rx) = mpsc::channel::<()>(1);
+    let changed = self.task_pool.changed.clone();
 
+    // Смена статусов будит цикл сразу, редкий тик нужен только спиннеру и таймерам
     tokio::spawn(async move {
-      let mut interval = tokio::time::interval(Duration::from_millis(33));
+      let mut interval = tokio::time::interval(FALLBACK_TICK);
       loop {
         interval.tick().await;
         if render_tx.send(()).await.is_err() {
@@ -108,9 +574,28 @@ impl App {
     });
 
     while self.model.running {
+      // Без терминала мерцать нечему, события и отчеты не задерживаются
+      let next_draw = self
+        .last_draw
+        .filter(|_| interactive)
+        .map(|last| last + self.redraw_interval);
+
       tokio::select! {
-        // Обновление состояния из TaskPool и рендеринг UI по тикам рендера
-        Some(_) = render_rx.recv() => {
+        // Обновление состояния из TaskPool и рендеринг UI по тикам и изменениям задач
+        _ = async {
+          tokio::select! {
+            _ = render_rx.recv() => {},
+            _ = changed.notified() => {
+              // Пачка изменений подряд дает один кадр, а после паузы кадр рисуется сразу
+              if let Some(next_draw) = next_draw {
+                tokio::time::sleep_until(next_draw).await;
+              }
+            },
+          }
+        } => {
+          let tick_start = Instant::now();
+          self.last_draw = Some(tick_start);
+
           // Обновляем состояние выполнения задач
           self.task_pool.pull_task().await?;
 
@@ -118,24 +603,91 @@ impl App {
           let start_time = self.start_time.unwrap_or_else(Instant::now);
 
           self.model.command_stats = self.task_pool.get_command_stats().await;
-          self.model.command_lines = self.task_pool.get_command_list().await;
+          self.model.command_usage = self.task_pool.get_command_usage().await;
+          self.model.command_lines = self
+            .task_pool
+            .get_command_list(&self.model.symbols, self.model.group_by)
+            .await;
+          self.model.slowest_tasks = self.task_pool.get_slowest_tasks(SLOWEST_TASKS_LIMIT).await;
           self.model.is_empty = self.task_pool.is_empty();
-          self.model.total_files = self.changed_files.len();
+          self.model.total_files = self.task_pool.file_count();
           self.model.total_execution_time = self.task_pool.get_total_execution_time().await;
-          self.model.statuses_count = self.task_pool.statuses().await.len();
+          let statuses = self.task_pool.statuses().await;
+          self.model.statuses_count = self.task_pool.task_count();
+          self.model.status_rollup = status_rollup(&statuses);
+          self.model.queue = QueueStats::from_statuses(&statuses);
+          self.model.running_task = statuses
+            .iter()
+            .position(|status| *status == CommandStatus::Running)
+            .map(|idx| {
+              let task = &self.task_pool.states[idx];
+              format!("{} on {}", task.command, task.filename)
+            });
+          self.model.groups = self
+            .task_pool
+            .group_views(
+              &self.model.command_lines,
+              &statuses,
+              self.model.task_sort,
+              self.model.group_by,
+            );
+          self.model.clamp_selection();
+          self.model.output_pane = match self.model.selected_task_index() {
+            Some(idx) => self.task_pool.output_view(idx).await,
+            None => None,
+          };
 
           if !done {
             self.model.elapsed_time = start_time.elapsed().as_millis();
+          } else if self.model.modified_files.is_none() {
+            let started = self.task_pool.started_files().await;
+            self.model.modified_files = Some(self.snapshot.modified_files(&started).await?);
+          }
+
+          let mode = if !interactive {
+            WaitOnExit::Off
+          } else {
+            self.exit_mode(&statuses)
+          };
+
+          if done {
+            self.model.awaiting_dismiss = mode == WaitOnExit::Manual;
+          }
+
+          if let Some(terminal) = terminal.as_mut() {
+            terminal.draw(|f| render_frame(f, &self.model))?;
+          }
+
+          if let Some(lines) = lines.as_mut() {
+            for line in lines.observe(&self.task_pool, &statuses).await {
+              println!("{}", line);
+            }
           }
 
-          terminal.draw(|f| render_frame(f, &self.model))?;
+          if let Some(progress) = progress.as_mut() {
+            for event in progress
+              .observe(&self.task_pool, &statuses, done, start_time.elapsed().as_millis())
+              .await {
+              println!("{}", serde_json::to_string(&event)?);
+            }
+          }
+
+          self.profile.record("render", tick_start.elapsed());
+          self.save_progress(&mut resume, &statuses)?;
+
+          if done {
+            if self.finished_at.is_none() {
+              self.profile.record("execute", execute_start.elapsed());
+            }
 
-          // if done {
-          //   println!("All done");
-          //   // Даем пользователю время увидеть результат и выходим
-          //   tokio::time::sleep(Duration::from_secs(5)).await;
-          //   self.quit();
-          // }
+            let finished_at = *self.finished_at.get_or_insert_with(Instant::now);
+
+            match mode {
+              WaitOnExit::Off => self.quit(),
+              WaitOnExit::Timed if finished_at.elapsed() >= self.linger => self.quit(),
+              _ => {}
+            }
+          }
         }
 
         // Обработка событий терминала и внутренних событий приложения
@@ -149,6 +701,15 @@ impl App {
               Key(key_event) if key_event.kind == KeyEventKind::Press => {
                 self.handle_key_events(key_event).await?;
               }
+              Resize(_, _) => {
+                // Перерисовываем сразу, не дожидаясь тика: старый кадр уже не совпадает с экраном
+                self.model.clamp_selection();
+
+                if let Some(terminal) = terminal.as_mut() {
+                  terminal.clear()?;
+                  terminal.draw(|f| render_frame(f, &self.model))?;
+                }
+              }
               _ => {}
             },
             Event::App(app_event) => match app_event {
@@ -159,26 +720,312 @@ impl App {
       };
     }
 
-    ratatui::restore();
+    if terminal.is_some() {
+      ratatui::restore();
+    }
+
+    if let Some(cache) = cache.as_mut() {
+      self.update_cache(cache).await?;
+    }
 
-    Ok(())
+    if let Some(path) = &self.html_report {
+      std::fs::write(self.root.join(path), html_report(&self.task_pool).await)?;
+    }
+
+    // Выход по `q` посреди запуска оставляет состояние для `--resume`
+    if self.task_pool.all_done().await? {
+      ResumeState::clear(&self.root)?;
+    }
+
+    Ok(true)
+  }
+
+  /// Finds the staged files, loads the config and matches files to commands.
+  ///
+  /// While files are being discovered the TUI shows a live count.
+  async fn prepare(
+    &mut self,
+    terminal: &mut Option<DefaultTerminal>,
+  ) -> color_eyre::Result<(OnEmpty, Vec<FileCommand>)> {
+    let phase = Instant::now();
+    let config = self.config_provider.load(&self.root)?;
+    // Паттерны нужны до чтения индекса: большой индекс сопоставляется по ходу чтения
+    let patterns = Arc::new(FilePatterns::new(&config, self.cli.mode)?);
+    self.profile.record("load config", phase.elapsed());
+
+    let phase = Instant::now();
+    let (count_tx, count_rx) = watch::channel(0);
+    let scan =
+      self
+        .file_provider
+        .changed_files_matched(&self.root, patterns.clone(), Some(count_tx));
+    tokio::pin!(scan);
+
+    let mut interval = tokio::time::interval(Duration::from_millis(33));
+    self.model.scanning = true;
+
+    // Пустой индекс - ошибка или тихий выход, решается после загрузки конфига
+    let prematched;
+    (self.changed_files, prematched) = loop {
+      tokio::select! {
+        changed_files = &mut scan => match changed_files {
+          Err(AppError::NoStagedFiles) => break (Vec::new(), HashMap::new()),
+          changed_files => break changed_files?,
+        },
+        _ = interval.tick() => {
+          self.model.total_files = *count_rx.borrow();
+
+          if let Some(terminal) = terminal.as_mut() {
+            terminal.draw(|f| render_frame(f, &self.model))?;
+          }
+        }
+      }
+    };
+
+    self.model.scanning = false;
+    self.profile.record("scan index", phase.elapsed());
+
+    if self.changed_files.is_empty() {
+      let fail_on_empty_commit = self
+        .cli
+        .fail_on_empty_commit
+        .or(config.fail_on_empty_commit)
+        .unwrap_or(true);
+
+      if fail_on_empty_commit {
+        return Err(AppError::NoStagedFiles.into());
+      }
+
+      return Ok((OnEmpty::Silent, Vec::new()));
+    }
+
+    let duplicates: Vec<String> = duplicate_patterns(&config.parse_groups(self.cli.mode)?)
+      .into_iter()
+      .map(|(pattern, groups)| {
+        format!(
+          "pattern '{}' is defined in groups '{}'; a file only runs the commands of one of them",
+          pattern,
+          groups.join("', '")
+        )
+      })
+      .collect();
+
+    if config.strict_patterns && !duplicates.is_empty() {
+      return Err(
+        AppError::DuplicatePatterns {
+          details: duplicates.join("\n"),
+        }
+        .into(),
+      );
+    }
+
+    self.warnings.extend(duplicates);
+
+    let on_empty = match self.cli.fail_on_no_match {
+      Some(true) => OnEmpty::Error,
+      Some(false) if config.on_empty == OnEmpty::Error => OnEmpty::Message,
+      _ => config.on_empty,
+    };
+
+    let phase = Instant::now();
+
+    let in_repo = self.file_provider.is_git_index();
+
+    // Переименования ищем только по запросу: это сравнение всего индекса с HEAD
+    if in_repo && config.renames != RenamePaths::New {
+      let renames = FileCommand::staged_renames(&self.root).await?;
+      self.changed_files = FileCommand::apply_renames(
+        std::mem::take(&mut self.changed_files),
+        &renames,
+        config.renames,
+      );
+    }
+
+    if in_repo && config.skip_ignored && !self.cli.force {
+      self.changed_files =
+        FileCommand::drop_ignored(&self.root, std::mem::take(&mut self.changed_files)).await?;
+    }
+
+    self.wait_on_exit = self.cli.wait_on_exit.or(config.wait_on_exit);
+    self.on_missing_command = config.on_missing_command;
+    self.max_failures = config
+      .parse_groups(self.cli.mode)?
+      .into_iter()
+      .map(|group| (group.name, group.max_failures))
+      .collect();
+    self.html_report = self.cli.html_report.clone().or(config.html_report.clone());
+
+    self.annotation_pattern = config
+      .annotation_pattern
+      .as_deref()
+      .map(Regex::new)
+      .transpose()
+      .map_err(|e| AppError::InvalidAnnotationPattern {
+        details: e.to_string(),
+      })?;
+
+    if let Some(redraw_interval_ms) = config.redraw_interval_ms {
+      self.redraw_interval = Duration::from_millis(redraw_interval_ms);
+    }
+
+    if let Some(linger_ms) = self.cli.linger_ms.or(config.linger_ms) {
+      self.linger = Duration::from_millis(linger_ms);
+    }
+
+    self.model.symbols = config
+      .symbols
+      .as_ref()
+      .map(SymbolsConfig::symbols)
+      .unwrap_or_default();
+
+    self.model.max_time = config
+      .max_time
+      .as_deref()
+      .and_then(|max_time| parse_duration::parse(max_time).ok())
+      .map(|max_time| max_time.as_millis());
+    self.model.hide_stats = self.cli.no_stats || config.show_stats == Some(false);
+    self.model.task_sort = self.cli.task_sort.or(config.task_sort).unwrap_or_default();
+    self.model.group_by = self.cli.group_by.or(config.group_by).unwrap_or_default();
+    self.model.stats_sort = self
+      .cli
+      .stats_sort
+      .or(config.stats_sort)
+      .unwrap_or_default();
+    self.model.stats_order = self
+      .cli
+      .stats_order
+      .or(config.stats_order)
+      .unwrap_or_else(|| self.model.stats_sort.default_order());
+
+    let matched = match FileCommand::match_prematched(
+      &config,
+      &patterns,
+      &self.changed_files,
+      &prematched,
+      self.cli.mode,
+    ) {
+      Err(AppError::NoFilesMatched { .. }) if on_empty != OnEmpty::Error => MatchedCommands {
+        file_commands: Vec::new(),
+        // Ни один файл не совпал, значит не использован ни один паттерн
+        unmatched_patterns: config
+          .parse_groups(self.cli.mode)?
+          .into_iter()
+          .flat_map(|group| {
+            let name = group.name;
+            group
+              .patterns
+              .into_keys()
+              .map(move |pattern| (name.clone(), pattern))
+          })
+          .collect(),
+      },
+      result => result?,
+    };
+    self.unused_patterns = matched.unmatched_patterns;
+    self.unused_patterns.sort();
+    let file_commands = matched.file_commands;
+    let mut file_commands = if in_repo {
+      FileCommand::drop_binary(&self.root, file_commands).await?
+    } else {
+      file_commands
+    };
+    self.model.group_file_counts = FileCommand::group_file_counts(&file_commands);
+
+    if !self.cli.passthrough.is_empty() {
+      for file_cmd in &mut file_commands {
+        file_cmd.append_args(&self.cli.passthrough);
+      }
+    }
+
+    FileCommand::attach_line_ranges(&self.root, &mut file_commands).await?;
+    self.profile.record("match files", phase.elapsed());
+
+    Ok((on_empty, file_commands))
+  }
+
+  /// Resolves the exit behavior: the configured one or, by default,
+  /// `manual` when something failed and `off` otherwise.
+  fn exit_mode(&self, statuses: &[CommandStatus]) -> WaitOnExit {
+    self.wait_on_exit.unwrap_or_else(|| {
+      let has_failures = statuses.iter().any(CommandStatus::is_failure);
+
+      if has_failures {
+        WaitOnExit::Manual
+      } else {
+        WaitOnExit::Off
+      }
+    })
   }
 
   /// Handles the key events and updates the state of [`App`].
   pub async fn handle_key_events(&mut self, key_event: KeyEvent) -> color_eyre::Result<()> {
-    match key_event.code {
-      KeyCode::Esc | KeyCode::Char('q') => self.events.send(AppEvent::Quit),
-      KeyCode::Char('c' | 'C') if key_event.modifiers == KeyModifiers::CONTROL => {
-        self.events.send(AppEvent::Quit)
+    // Клавиши и их контекст описаны в `KEYMAP`, по нему же рисуется строка подсказок.
+    // Навигация работает и на итоговом экране, а он закрывается любой другой клавишей
+    match action_for(&key_event, self.model.awaiting_dismiss) {
+      Some(Action::SelectPrevious) => self.model.select_previous(),
+      Some(Action::SelectNext) => self.model.select_next(),
+      Some(Action::ToggleGroup) => self.model.toggle_selected_group(),
+      Some(Action::ToggleGroupBy) => self.model.toggle_group_by(),
+      Some(Action::Quit) => self.events.send(AppEvent::Quit),
+      None => {}
+    }
+
+    Ok(())
+  }
+
+  /// Saves tasks that passed since the last tick, so an interrupted run can be resumed.
+  fn save_progress(&self, resume: &mut ResumeState, statuses: &[CommandStatus]) -> Result<()> {
+    let mut changed = false;
+
+    for (idx, (task, status)) in self.task_pool.states.iter().zip(statuses).enumerate() {
+      if !matches!(
+        status,
+        CommandStatus::Done | CommandStatus::DoneWithWarnings
+      ) {
+        continue;
+      }
+
+      if let Some(hash) = self.snapshot.hash(&task.filename) {
+        changed |= resume.record(idx, &task.filename, &task.command, hash);
       }
-      // Other handlers you could add here.
-      _ => {}
     }
+
+    if changed {
+      resume.save(&self.root)?;
+    }
+
     Ok(())
   }
 
+  /// Records successful tasks in the `--since-last-run` cache and drops the rest.
+  async fn update_cache(&self, cache: &mut RunCache) -> Result<()> {
+    let statuses = self.task_pool.statuses().await;
+
+    for (task, status) in self.task_pool.states.iter().zip(&statuses) {
+      // Хеш снят до запуска: если команда изменила файл, следующий запуск промахнется
+      match (status, self.snapshot.hash(&task.filename)) {
+        (CommandStatus::Skipped, _) => {}
+        (CommandStatus::Done | CommandStatus::DoneWithWarnings, Some(hash)) => {
+          cache.record(&task.filename, &task.command, hash)
+        }
+        _ => cache.forget(&task.filename, &task.command),
+      }
+    }
+
+    cache.save(&self.root)
+  }
+
   /// Set running to false to quit the application.
   pub fn quit(&mut self) {
     self.model.running = false;
   }
 }
+
+/// Where the files come from: the index, or the working tree with `--changed-only`.
+fn file_provider(cli: &Cli) -> Box<dyn FileProvider> {
+  if cli.changed_only {
+    Box::new(WorkingTree)
+  } else {
+    Box::new(GitIndex)
+  }
+}
@@ -0,0 +1,123 @@
+use crate::app::Result;
+use crate::lock::create_state_dir;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// Where the `--since-last-run` cache is stored, relative to the repository root.
+pub const CACHE_PATH: &str = ".fast-staged/cache.json";
+
+/// Where passed tasks of an unfinished run are kept for `--resume`.
+pub const RESUME_PATH: &str = ".fast-staged/resume.json";
+
+/// Content hashes of files that passed a command on a previous run.
+///
+/// Keyed by file and then by the full command string, so editing a command
+/// invalidates its entries.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RunCache {
+  files: HashMap<String, HashMap<String, String>>,
+}
+
+impl RunCache {
+  /// Reads the cache of the repository at `root`, starting from scratch if it is
+  /// missing or unreadable.
+  pub fn load(root: &Path) -> Self {
+    Self::load_from(&root.join(CACHE_PATH))
+  }
+
+  pub fn save(&self, root: &Path) -> Result<()> {
+    self.save_to(root, CACHE_PATH)
+  }
+
+  fn load_from(path: &Path) -> Self {
+    fs::read_to_string(path)
+      .ok()
+      .and_then(|content| serde_json::from_str(&content).ok())
+      .unwrap_or_default()
+  }
+
+  fn save_to(&self, root: &Path, path: &str) -> Result<()> {
+    create_state_dir(root)?;
+
+    fs::write(root.join(path), serde_json::to_string(self)?)?;
+
+    Ok(())
+  }
+
+  /// Checks whether `command` already succeeded for this exact file content.
+  pub fn is_fresh(&self, file: &str, command: &str, hash: &str) -> bool {
+    self
+      .files
+      .get(file)
+      .and_then(|commands| commands.get(command))
+      .is_some_and(|cached| cached == hash)
+  }
+
+  pub fn record(&mut self, file: &str, command: &str, hash: String) {
+    self
+      .files
+      .entry(file.to_string())
+      .or_default()
+      .insert(command.to_string(), hash);
+  }
+
+  pub fn forget(&mut self, file: &str, command: &str) {
+    if let Some(commands) = self.files.get_mut(file) {
+      commands.remove(command);
+
+      if commands.is_empty() {
+        self.files.remove(file);
+      }
+    }
+  }
+}
+
+/// Tasks of the current run that passed, saved as they finish.
+///
+/// If the run is interrupted, `--resume` skips these tasks for unchanged files.
+/// A run that finishes every task removes the file.
+#[derive(Debug, Default)]
+pub struct ResumeState {
+  passed: RunCache,
+  // Индексы задач, уже записанных в этом запуске
+  recorded: HashSet<usize>,
+}
+
+impl ResumeState {
+  /// Continues from the state of an interrupted run in the repository at `root`.
+  pub fn load(root: &Path) -> Self {
+    Self {
+      passed: RunCache::load_from(&root.join(RESUME_PATH)),
+      recorded: HashSet::new(),
+    }
+  }
+
+  pub fn is_fresh(&self, file: &str, command: &str, hash: &str) -> bool {
+    self.passed.is_fresh(file, command, hash)
+  }
+
+  /// Records a passed task once; returns whether it was new.
+  pub fn record(&mut self, task: usize, file: &str, command: &str, hash: String) -> bool {
+    if !self.recorded.insert(task) {
+      return false;
+    }
+
+    self.passed.record(file, command, hash);
+
+    true
+  }
+
+  pub fn save(&self, root: &Path) -> Result<()> {
+    self.passed.save_to(root, RESUME_PATH)
+  }
+
+  /// Removes the state once the run has finished every task.
+  pub fn clear(root: &Path) -> Result<()> {
+    match fs::remove_file(root.join(RESUME_PATH)) {
+      Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+      _ => Ok(()),
+    }
+  }
+}
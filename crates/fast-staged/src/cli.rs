@@ -0,0 +1,124 @@
+use crate::config::{GroupBy, Mode, SortOrder, StatsSort, TaskSort, WaitOnExit};
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+/// Command line arguments.
+#[derive(Debug, Default, Clone, Parser)]
+#[command(name = "fast-staged", version, about = "Run commands on staged files")]
+pub struct Cli {
+  #[command(subcommand)]
+  pub command: Option<Command>,
+
+  /// Print a JSON report of all tasks after the run.
+  #[arg(long)]
+  pub json: bool,
+
+  /// Stream task progress as newline-delimited JSON events to stdout instead of the TUI.
+  #[arg(long, conflicts_with = "json")]
+  pub ndjson: bool,
+
+  /// What to do with the TUI once all tasks are finished.
+  #[arg(long, value_enum)]
+  pub wait_on_exit: Option<WaitOnExit>,
+
+  /// How long the final frame stays on screen in `timed` mode, in milliseconds (default 500).
+  #[arg(long, value_name = "MS")]
+  pub linger_ms: Option<u64>,
+
+  /// Which command variants to run for patterns with separate `fix` and `check` lists.
+  #[arg(long, value_enum, default_value_t = Mode::Fix)]
+  pub mode: Mode,
+
+  /// Order of tasks within each group of the task list.
+  #[arg(long, value_enum)]
+  pub task_sort: Option<TaskSort>,
+
+  /// Group the task list by config group or by file; `g` switches it in the TUI.
+  #[arg(long, value_enum)]
+  pub group_by: Option<GroupBy>,
+
+  /// Sort key for the command statistics panel.
+  #[arg(long, value_enum)]
+  pub stats_sort: Option<StatsSort>,
+
+  /// Sort direction for the command statistics panel.
+  #[arg(long, value_enum)]
+  pub stats_order: Option<SortOrder>,
+
+  /// Hide the command statistics panel and give its space to the task list.
+  #[arg(long)]
+  pub no_stats: bool,
+
+  /// Fail when nothing is staged (default true); `=false` exits successfully instead,
+  /// like `git commit --allow-empty`.
+  #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+  pub fail_on_empty_commit: Option<bool>,
+
+  /// Fail when staged files match no pattern, overriding `on_empty`;
+  /// `=false` turns a configured error into a notice.
+  #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+  pub fail_on_no_match: Option<bool>,
+
+  /// Run on files changed in the working tree (modified, untracked) instead of staged ones.
+  #[arg(long)]
+  pub changed_only: bool,
+
+  /// Run even if a merge, rebase or similar operation is in progress,
+  /// and keep ignored files when `skip_ignored` is set.
+  #[arg(long)]
+  pub force: bool,
+
+  /// Path inside the git repository to run in; defaults to the current directory.
+  ///
+  /// Commands, config discovery and staged paths are all resolved from the repository root.
+  /// Repeat to run in several repositories in parallel and get a combined report.
+  #[arg(long, value_name = "PATH")]
+  pub repo: Vec<PathBuf>,
+
+  /// Skip commands that already passed for the same file content on a previous run.
+  #[arg(long)]
+  pub since_last_run: bool,
+
+  /// Skip tasks that passed in an interrupted previous run, if their files are unchanged.
+  #[arg(long)]
+  pub resume: bool,
+
+  /// Print the configured groups with their patterns, commands, timeout and order, then exit.
+  #[arg(long)]
+  pub list_groups: bool,
+
+  /// Print the commands that would run for each staged file, then exit without running them.
+  ///
+  /// Commands that come from `overrides` are marked as such.
+  #[arg(long)]
+  pub dry_run: bool,
+
+  /// List configured patterns that matched no staged file, to spot dead config.
+  #[arg(long)]
+  pub unused_patterns: bool,
+
+  /// Write a self-contained HTML report of the run to this path.
+  #[arg(long, value_name = "PATH")]
+  pub html_report: Option<PathBuf>,
+
+  /// Print a timing breakdown of each phase of the run to stderr.
+  #[arg(long)]
+  pub profile: bool,
+
+  /// Extra arguments appended to every matched command, e.g. `fast-staged -- --fix`.
+  #[arg(last = true)]
+  pub passthrough: Vec<String>,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+  /// Print a JSON Schema of the config file for editor completion and validation.
+  Schema,
+
+  /// Write a starter `.fast-staged.toml` for the project in the current directory.
+  Init {
+    /// Overwrite an existing config.
+    #[arg(long)]
+    force: bool,
+  },
+}
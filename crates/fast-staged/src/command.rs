@@ -7,9 +7,35 @@ pub enum CommandStatus {
   Waiting,
   Running,
   Done,
+  // Успешно, но вывод совпал с `warning_pattern`
+  DoneWithWarnings,
   Failed(String),
   Timeout,
-  // Cancelled,
+  // Результат взят из кеша `--since-last-run`
+  Skipped,
+  // Не запускалась или прервана: другая команда группы упала
+  // при `stop_on_error` или `group_fail_fast`
+  Cancelled,
+}
+
+impl CommandStatus {
+  /// Whether the task failed or timed out.
+  pub fn is_failure(&self) -> bool {
+    matches!(self, CommandStatus::Failed(_) | CommandStatus::Timeout)
+  }
+
+  /// Whether the task reached a final state.
+  pub fn is_finished(&self) -> bool {
+    matches!(
+      self,
+      CommandStatus::Done
+        | CommandStatus::DoneWithWarnings
+        | CommandStatus::Failed(_)
+        | CommandStatus::Timeout
+        | CommandStatus::Skipped
+        | CommandStatus::Cancelled
+    )
+  }
 }
 
 impl std::fmt::Display for CommandStatus {
@@ -19,26 +45,126 @@ impl std::fmt::Display for CommandStatus {
       CommandStatus::Waiting => write!(f, "Waiting"),
       CommandStatus::Running => write!(f, "Running"),
       CommandStatus::Done => write!(f, "Done"),
+      CommandStatus::DoneWithWarnings => write!(f, "Done with warnings"),
       CommandStatus::Failed(_) => write!(f, "Failed"),
       CommandStatus::Timeout => write!(f, "Timeout"),
-      // CommandStatus::Cancelled => write!(f, "Cancelled"),
+      CommandStatus::Skipped => write!(f, "Skipped"),
+      CommandStatus::Cancelled => write!(f, "Cancelled"),
     }
   }
 }
 
+/// Glyphs used for task statuses and group markers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbols {
+  pub done: String,
+  pub warning: String,
+  pub failed: String,
+  pub running: String,
+  pub waiting: String,
+  pub timeout: String,
+  pub skipped: String,
+  pub cancelled: String,
+  pub expanded: String,
+  pub collapsed: String,
+  pub ellipsis: String,
+}
+
+impl Symbols {
+  pub fn unicode() -> Self {
+    Self {
+      done: "✓".to_string(),
+      warning: "⚠".to_string(),
+      failed: "✗".to_string(),
+      running: "⟳".to_string(),
+      waiting: "⏳".to_string(),
+      timeout: "⏱".to_string(),
+      skipped: "↷".to_string(),
+      cancelled: "⊘".to_string(),
+      expanded: "▾".to_string(),
+      collapsed: "▸".to_string(),
+      ellipsis: "…".to_string(),
+    }
+  }
+
+  /// Plain ASCII for terminals and fonts without the Unicode glyphs.
+  pub fn ascii() -> Self {
+    Self {
+      done: "[OK]".to_string(),
+      warning: "[WARN]".to_string(),
+      failed: "[X]".to_string(),
+      running: "[..]".to_string(),
+      waiting: "[--]".to_string(),
+      timeout: "[TIME]".to_string(),
+      skipped: "[SKIP]".to_string(),
+      cancelled: "[STOP]".to_string(),
+      expanded: "v".to_string(),
+      collapsed: ">".to_string(),
+      ellipsis: "...".to_string(),
+    }
+  }
+}
+
+impl Default for Symbols {
+  fn default() -> Self {
+    Self::unicode()
+  }
+}
+
 pub trait StatusDisplay {
-  fn colored(&self) -> (&str, Color);
+  fn colored<'a>(&self, symbols: &'a Symbols) -> (&'a str, Color);
 }
 
 impl StatusDisplay for CommandStatus {
-  fn colored(&self) -> (&str, Color) {
+  fn colored<'a>(&self, symbols: &'a Symbols) -> (&'a str, Color) {
     match self {
-      CommandStatus::None => ("⏳", Color::Gray),
-      CommandStatus::Done => ("✓", Color::Green),
-      CommandStatus::Failed(_) => ("✗", Color::Red),
-      CommandStatus::Running => ("⟳", Color::Yellow),
-      CommandStatus::Waiting => ("⏳", Color::Gray),
-      CommandStatus::Timeout => ("⏱", Color::Magenta),
+      CommandStatus::None => (&symbols.waiting, Color::Gray),
+      CommandStatus::Done => (&symbols.done, Color::Green),
+      CommandStatus::DoneWithWarnings => (&symbols.warning, Color::Yellow),
+      CommandStatus::Failed(_) => (&symbols.failed, Color::Red),
+      CommandStatus::Running => (&symbols.running, Color::Yellow),
+      CommandStatus::Waiting => (&symbols.waiting, Color::Gray),
+      CommandStatus::Timeout => (&symbols.timeout, Color::Magenta),
+      CommandStatus::Skipped => (&symbols.skipped, Color::DarkGray),
+      CommandStatus::Cancelled => (&symbols.cancelled, Color::DarkGray),
     }
   }
 }
+
+/// Builds a one-line rollup like "12 done, 2 failed, 1 timeout", skipping zero counts.
+pub fn status_rollup(statuses: &[CommandStatus]) -> String {
+  let labels = [
+    "done",
+    "done with warnings",
+    "failed",
+    "timeout",
+    "skipped",
+    "cancelled",
+    "running",
+    "waiting",
+  ];
+  let mut counts = [0usize; 8];
+
+  for status in statuses {
+    let idx = match status {
+      CommandStatus::Done => 0,
+      CommandStatus::DoneWithWarnings => 1,
+      CommandStatus::Failed(_) => 2,
+      CommandStatus::Timeout => 3,
+      CommandStatus::Skipped => 4,
+      CommandStatus::Cancelled => 5,
+      CommandStatus::Running => 6,
+      CommandStatus::Waiting | CommandStatus::None => 7,
+    };
+
+    counts[idx] += 1;
+  }
+
+  labels
+    .iter()
+    .zip(counts)
+    .filter(|(_, count)| *count > 0)
+    .map(|(label, count)| format!("{} {}", count, label))
+    .collect::<Vec<_>>()
+    .join(", ")
+}
@@ -1,5 +1,10 @@
 use crate::app::AppError;
 use crate::app::Result;
+use crate::command::{CommandStatus, Symbols};
+use crate::file::shell_quote;
+use crate::matcher::{expand_braces, ignore_globs};
+use crate::validate::check_config;
+use regex::Regex;
 use serde::Deserialize;
 use serde_json::Value;
 use std::collections::HashMap;
@@ -9,7 +14,158 @@ use std::path::PathBuf;
 use toml;
 
 type FilePattern = String;
-type CommandList = Vec<String>;
+type CommandList = Vec<CommandSpec>;
+type SourceFn = fn(PathBuf) -> ConfigSource;
+
+/// Name of the fallback group: its patterns are checked only for files no other group matched.
+pub const DEFAULT_GROUP: &str = "default";
+
+/// Group that runs overrides for files no group pattern matched.
+pub const OVERRIDES_GROUP: &str = "overrides";
+
+/// File next to the config with glob patterns excluded from all groups.
+pub const IGNORE_FILE: &str = ".fast-stagedignore";
+
+/// Extra attempts for a command with `retry_on` when `retries` is not set.
+pub const DEFAULT_RETRIES: usize = 1;
+
+/// Inline TOML or JSON config that replaces config file discovery.
+pub const CONFIG_ENV: &str = "FAST_STAGED_CONFIG";
+
+/// Format of `FAST_STAGED_CONFIG`: "toml" or "json", guessed from the content if unset.
+pub const CONFIG_FORMAT_ENV: &str = "FAST_STAGED_CONFIG_FORMAT";
+
+/// Config files checked during discovery when `FAST_STAGED_CONFIG` is unset, in priority order.
+pub const CONFIG_FILES: &[(&str, SourceFn)] = &[
+  (".fast-staged.toml", ConfigSource::TomlFile),
+  ("fast-staged.toml", ConfigSource::TomlFile),
+  (".fast-staged.json", ConfigSource::JsonFile),
+  ("fast-staged.json", ConfigSource::JsonFile),
+  ("package.json", ConfigSource::PackageJson),
+  ("Cargo.toml", ConfigSource::CargoToml),
+  ("deno.json", ConfigSource::DenoJson),
+];
+
+/// Commands for a pattern: one list for every mode, or separate lists per `--mode`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum PatternCommands {
+  Shared(CommandList),
+  PerMode(ModeCommands),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModeCommands {
+  #[serde(default)]
+  fix: CommandList,
+  #[serde(default)]
+  check: CommandList,
+}
+
+impl PatternCommands {
+  pub fn for_mode(&self, mode: Mode) -> &CommandList {
+    match (self, mode) {
+      (PatternCommands::Shared(commands), _) => commands,
+      (PatternCommands::PerMode(commands), Mode::Fix) => &commands.fix,
+      (PatternCommands::PerMode(commands), Mode::Check) => &commands.check,
+    }
+  }
+}
+
+/// A command as written in the config.
+///
+/// A string runs through `sh -c`, a list is executed directly as argv without a shell.
+/// A table wraps either form with per-command options, or chains several steps
+/// that run one after another, each with its own status.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum CommandSpec {
+  Shell(String),
+  Argv(Vec<String>),
+  Detailed {
+    run: Box<CommandSpec>,
+    // Ошибка этой команды не останавливает группу со `stop_on_error`
+    #[serde(default)]
+    continue_on_error: bool,
+    // "file" - содержимое файла подается в stdin, а stdout записывается обратно в файл
+    #[serde(default)]
+    stdin: StdinMode,
+    // Коды выхода, которые считаются успехом вдобавок к 0, например [1] для "нет изменений"
+    #[serde(default)]
+    success_codes: Vec<i32>,
+  },
+  Chain {
+    // Шаги выполняются по очереди, ошибка шага отменяет следующие
+    chain: Vec<CommandSpec>,
+  },
+}
+
+impl CommandSpec {
+  /// Returns the command as a single line, quoting argv items the way a shell would need them.
+  pub fn to_command_line(&self) -> String {
+    match self {
+      CommandSpec::Shell(command) => command.clone(),
+      CommandSpec::Argv(argv) => argv
+        .iter()
+        .map(|arg| shell_quote(arg))
+        .collect::<Vec<_>>()
+        .join(" "),
+      CommandSpec::Detailed { run, .. } => run.to_command_line(),
+      CommandSpec::Chain { chain } => chain
+        .iter()
+        .map(CommandSpec::to_command_line)
+        .collect::<Vec<_>>()
+        .join(" && "),
+    }
+  }
+
+  /// Splits a chain into its steps; any other command is a single step.
+  pub fn steps(&self) -> Vec<&CommandSpec> {
+    match self {
+      CommandSpec::Chain { chain } => chain.iter().flat_map(CommandSpec::steps).collect(),
+      _ => vec![self],
+    }
+  }
+
+  pub fn is_chain(&self) -> bool {
+    matches!(self, CommandSpec::Chain { .. })
+  }
+
+  pub fn argv(&self) -> Option<Vec<String>> {
+    match self {
+      CommandSpec::Shell(_) => None,
+      CommandSpec::Argv(argv) => Some(argv.clone()),
+      CommandSpec::Detailed { run, .. } => run.argv(),
+      CommandSpec::Chain { .. } => None,
+    }
+  }
+
+  pub fn stdin(&self) -> StdinMode {
+    match self {
+      CommandSpec::Detailed { stdin, .. } => *stdin,
+      _ => StdinMode::None,
+    }
+  }
+
+  /// Exit codes that count as success; empty means only 0.
+  pub fn success_codes(&self) -> Vec<i32> {
+    match self {
+      CommandSpec::Detailed { success_codes, .. } => success_codes.clone(),
+      _ => Vec::new(),
+    }
+  }
+
+  pub fn continue_on_error(&self) -> bool {
+    matches!(
+      self,
+      CommandSpec::Detailed {
+        continue_on_error: true,
+        ..
+      }
+    )
+  }
+}
 
 #[derive(Debug, Clone, Copy, Deserialize)]
 pub enum ExecutionOrder {
@@ -19,12 +175,363 @@ pub enum ExecutionOrder {
   Sequential,
 }
 
+/// What to do with the TUI once all tasks are finished.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, clap::ValueEnum)]
+pub enum WaitOnExit {
+  /// Exit right after the final frame.
+  #[serde(rename = "off")]
+  Off,
+  /// Keep the final frame for a short delay.
+  #[serde(rename = "timed")]
+  Timed,
+  /// Keep the final frame until a key is pressed.
+  #[serde(rename = "manual")]
+  Manual,
+}
+
+/// Which command variants to run for patterns that define both.
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum Mode {
+  /// Run commands that rewrite files.
+  #[default]
+  Fix,
+  /// Run commands that only report problems.
+  Check,
+}
+
+/// Sort key for the command statistics panel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, clap::ValueEnum)]
+pub enum StatsSort {
+  /// Command name, alphabetically.
+  #[default]
+  #[serde(rename = "name")]
+  Name,
+  /// Total time spent in the command.
+  #[serde(rename = "total")]
+  Total,
+  /// Average time per execution.
+  #[serde(rename = "avg")]
+  Avg,
+  /// Number of executions.
+  #[serde(rename = "count")]
+  Count,
+}
+
+impl StatsSort {
+  /// Names read best A to Z, numbers read best largest first.
+  pub fn default_order(self) -> SortOrder {
+    match self {
+      StatsSort::Name => SortOrder::Asc,
+      _ => SortOrder::Desc,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, clap::ValueEnum)]
+pub enum SortOrder {
+  #[default]
+  #[serde(rename = "asc")]
+  Asc,
+  #[serde(rename = "desc")]
+  Desc,
+}
+
+/// How often a group's commands run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub enum Scope {
+  /// Once for every matched file.
+  #[default]
+  #[serde(rename = "file")]
+  File,
+  /// Once for the whole group if any file matched.
+  #[serde(rename = "group")]
+  Group,
+}
+
+/// Which outcomes of a command are retried.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub enum RetryOn {
+  #[default]
+  #[serde(rename = "never")]
+  Never,
+  /// Only timeouts, for commands that are slow now and then.
+  #[serde(rename = "timeout")]
+  Timeout,
+  /// Only failures, timeouts are final.
+  #[serde(rename = "failure")]
+  Failure,
+  #[serde(rename = "any")]
+  Any,
+}
+
+impl RetryOn {
+  pub fn matches(self, status: &CommandStatus) -> bool {
+    matches!(
+      (self, status),
+      (RetryOn::Timeout | RetryOn::Any, CommandStatus::Timeout)
+        | (RetryOn::Failure | RetryOn::Any, CommandStatus::Failed(_))
+    )
+  }
+}
+
+/// What the timeout of a command is measured against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub enum TimeoutClock {
+  #[default]
+  #[serde(rename = "wall")]
+  Wall,
+  /// CPU time of the command and its descendants, so a loaded machine does not
+  /// time out commands that are merely waiting. Linux only, elsewhere wall time.
+  #[serde(rename = "cpu")]
+  Cpu,
+}
+
+/// Built-in glyph sets for task statuses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub enum SymbolSet {
+  #[default]
+  #[serde(rename = "unicode")]
+  Unicode,
+  #[serde(rename = "ascii")]
+  Ascii,
+}
+
+/// The `symbols` option: a built-in set, or a table overriding single glyphs of a set.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum SymbolsConfig {
+  Preset(SymbolSet),
+  Custom(Box<CustomSymbols>),
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomSymbols {
+  #[serde(default)]
+  preset: SymbolSet,
+  done: Option<String>,
+  warning: Option<String>,
+  failed: Option<String>,
+  running: Option<String>,
+  waiting: Option<String>,
+  timeout: Option<String>,
+  skipped: Option<String>,
+  cancelled: Option<String>,
+  expanded: Option<String>,
+  collapsed: Option<String>,
+  ellipsis: Option<String>,
+}
+
+impl SymbolSet {
+  fn symbols(self) -> Symbols {
+    match self {
+      SymbolSet::Unicode => Symbols::unicode(),
+      SymbolSet::Ascii => Symbols::ascii(),
+    }
+  }
+}
+
+impl SymbolsConfig {
+  pub fn symbols(&self) -> Symbols {
+    match self {
+      SymbolsConfig::Preset(set) => set.symbols(),
+      SymbolsConfig::Custom(custom) => {
+        let mut symbols = custom.preset.symbols();
+        let overrides = [
+          (&mut symbols.done, &custom.done),
+          (&mut symbols.warning, &custom.warning),
+          (&mut symbols.failed, &custom.failed),
+          (&mut symbols.running, &custom.running),
+          (&mut symbols.waiting, &custom.waiting),
+          (&mut symbols.timeout, &custom.timeout),
+          (&mut symbols.skipped, &custom.skipped),
+          (&mut symbols.cancelled, &custom.cancelled),
+          (&mut symbols.expanded, &custom.expanded),
+          (&mut symbols.collapsed, &custom.collapsed),
+          (&mut symbols.ellipsis, &custom.ellipsis),
+        ];
+
+        for (symbol, custom) in overrides {
+          if let Some(custom) = custom {
+            *symbol = custom.clone();
+          }
+        }
+
+        symbols
+      }
+    }
+  }
+}
+
+/// Which paths of a file renamed in the index commands run on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub enum RenamePaths {
+  /// The new path, as the index reports it.
+  #[default]
+  #[serde(rename = "new")]
+  New,
+  /// The path before the rename.
+  #[serde(rename = "old")]
+  Old,
+  /// Both paths.
+  #[serde(rename = "both")]
+  Both,
+}
+
+/// Order of tasks within a group in the task list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, clap::ValueEnum)]
+pub enum TaskSort {
+  /// Failed and running tasks first, finished ones last.
+  #[default]
+  #[serde(rename = "status")]
+  Status,
+  /// Slowest first.
+  #[serde(rename = "duration")]
+  Duration,
+  /// By file name.
+  #[serde(rename = "file")]
+  File,
+  /// In the order tasks were created.
+  #[serde(rename = "none")]
+  None,
+}
+
+/// `fail_on_output` of a group: a regex for the output, or `true` for any output.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum FailOnOutput {
+  Any(bool),
+  Pattern(String),
+}
+
+impl FailOnOutput {
+  /// The regex to check the output with, `None` when disabled.
+  fn pattern(&self) -> Option<&str> {
+    match self {
+      // Хоть один непробельный символ
+      FailOnOutput::Any(true) => Some(r"\S"),
+      FailOnOutput::Any(false) => None,
+      FailOnOutput::Pattern(pattern) => Some(pattern),
+    }
+  }
+}
+
+/// `clean_env` of a group: `true` for PATH only, or the variables to pass through.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum CleanEnv {
+  Enabled(bool),
+  Allow(Vec<String>),
+}
+
+impl CleanEnv {
+  /// Variables inherited besides PATH, `None` when the whole environment is.
+  fn allowlist(&self) -> Option<Vec<String>> {
+    match self {
+      CleanEnv::Enabled(true) => Some(Vec::new()),
+      CleanEnv::Enabled(false) => None,
+      CleanEnv::Allow(names) => Some(names.clone()),
+    }
+  }
+}
+
+/// What a command gets on stdin.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub enum StdinMode {
+  /// Nothing, stdin is closed.
+  #[default]
+  #[serde(rename = "none")]
+  None,
+  /// The content of the file, for formatters that read stdin and print the result.
+  ///
+  /// On success stdout replaces the file. Commands with `scope = "group"` or
+  /// `tty = true` get nothing.
+  #[serde(rename = "file")]
+  File,
+}
+
+/// What the task list is grouped by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, clap::ValueEnum)]
+pub enum GroupBy {
+  /// Config groups, with the tasks of every file matched by the group.
+  #[default]
+  #[serde(rename = "group")]
+  Group,
+  /// Files, with every command that runs on the file.
+  #[serde(rename = "file")]
+  File,
+}
+
+impl GroupBy {
+  pub fn toggled(self) -> Self {
+    match self {
+      GroupBy::Group => GroupBy::File,
+      GroupBy::File => GroupBy::Group,
+    }
+  }
+}
+
+/// What to do when no command matches the staged files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub enum OnEmpty {
+  /// Exit successfully without output.
+  #[default]
+  #[serde(rename = "silent")]
+  Silent,
+  /// Print a short notice and exit successfully.
+  #[serde(rename = "message")]
+  Message,
+  /// Fail the run.
+  #[serde(rename = "error")]
+  Error,
+}
+
+/// What to do when a command is not found in PATH.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub enum OnMissingCommand {
+  /// Fail the run before any command starts.
+  #[default]
+  #[serde(rename = "error")]
+  Error,
+  /// Skip the tasks of the missing command.
+  #[serde(rename = "skip")]
+  Skip,
+  /// Skip the tasks and print a warning after the run.
+  #[serde(rename = "warn")]
+  Warn,
+}
+
 #[derive(Debug, Clone)]
 pub struct Group {
   pub name: String,
   pub patterns: HashMap<FilePattern, CommandList>,
   pub timeout: Option<String>,
+  pub kill_grace: Option<String>,
   pub execution_order: ExecutionOrder,
+  pub mutex: Option<String>,
+  pub only_changed_lines: bool,
+  pub env: HashMap<String, String>,
+  pub clean_env: Option<Vec<String>>,
+  pub warning_pattern: Option<Regex>,
+  pub fail_on_output: Option<Regex>,
+  pub filter_output: Vec<Regex>,
+  pub capture: Option<Regex>,
+  pub stop_on_error: bool,
+  pub group_fail_fast: bool,
+  pub output_limit_kb: Option<usize>,
+  pub priority: i32,
+  pub scope: Scope,
+  pub binary: bool,
+  pub tty: bool,
+  pub absolute_paths: bool,
+  pub retry_on: RetryOn,
+  pub timeout_clock: TimeoutClock,
+  pub retries: usize,
+  pub max_failures: usize,
+  pub fail_on_changes: bool,
+  pub nice: Option<i32>,
+  pub command_prefix: Vec<String>,
+  pub when: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -32,6 +539,24 @@ pub enum ConfigSource {
   TomlFile(PathBuf),
   JsonFile(PathBuf),
   PackageJson(PathBuf),
+  CargoToml(PathBuf),
+  DenoJson(PathBuf),
+  // Содержимое `FAST_STAGED_CONFIG`, файл не нужен
+  Env(String),
+}
+
+impl ConfigSource {
+  /// Config file path; `None` for a config passed in the environment.
+  pub fn path(&self) -> Option<&Path> {
+    match self {
+      ConfigSource::TomlFile(path)
+      | ConfigSource::JsonFile(path)
+      | ConfigSource::PackageJson(path)
+      | ConfigSource::CargoToml(path)
+      | ConfigSource::DenoJson(path) => Some(path),
+      ConfigSource::Env(_) => None,
+    }
+  }
 }
 
 #[derive(Debug, Deserialize)]
@@ -40,15 +565,158 @@ pub struct Config {
   #[serde(default)]
   timeout: Option<String>,
 
+  // Сколько ждать после SIGTERM перед SIGKILL при timeout (опционально)
+  #[serde(default)]
+  kill_grace: Option<String>,
+
+  // Сколько килобайт вывода команды хранить (последние), по умолчанию 64
+  #[serde(default)]
+  output_limit_kb: Option<usize>,
+
+  // Понижение приоритета команд (nice, 1-19), чтобы редактор не тормозил
+  // во время большой проверки; на Windows любое значение больше 0 - BELOW_NORMAL
+  #[serde(default)]
+  nice: Option<i32>,
+
+  // Обертка для всех команд, например ["nix", "develop", "-c"]
+  // или ["docker", "compose", "run", "app"]; строка-команда дописывается после нее
+  // в `sh -c`, поэтому в "a && b" обернута только `a`
+  #[serde(default)]
+  command_prefix: Option<Vec<String>>,
+
   // Порядок выполнения команд в группе
   // "parallel" (по умолчанию) или "sequential"
   #[serde(default)]
   execution_order: Option<ExecutionOrder>,
 
+  // Ошибка, если staged файл не попал ни в одну группу
+  #[serde(default)]
+  pub strict_coverage: bool,
+
+  // Ошибка вместо предупреждения, если один паттерн задан в нескольких группах
+  #[serde(default)]
+  pub strict_patterns: bool,
+
+  // Пропускать staged файлы, которые попадают под .gitignore
+  // (например, добавленные через `git add -f`), если не указан --force
+  #[serde(default)]
+  pub skip_ignored: bool,
+
+  // Для переименованных в индексе файлов: "new" (по умолчанию), "old" или "both"
+  #[serde(default)]
+  pub renames: RenamePaths,
+
+  // Регулярное выражение для строк вывода, которые в GitHub Actions становятся аннотациями,
+  // с именованными группами file, line, col и message
+  #[serde(default)]
+  pub annotation_pattern: Option<String>,
+
+  // Куда записать HTML-отчет о запуске (таблица задач с выводом), путь от корня репозитория
+  #[serde(default)]
+  pub html_report: Option<PathBuf>,
+
+  // Поведение TUI после завершения всех задач
+  // По умолчанию "manual" при ошибках и "off" если все успешно
+  #[serde(default)]
+  pub wait_on_exit: Option<WaitOnExit>,
+
+  // Сколько миллисекунд держать итоговый кадр в режиме "timed" (по умолчанию 500),
+  // 0 - закрывать сразу после последнего кадра
+  #[serde(default)]
+  pub linger_ms: Option<u64>,
+
+  // Бюджет времени на весь запуск, например "2m": в заголовке TUI идет обратный отсчет.
+  // Запуск не прерывается, это только подсказка, когда пора отменять медленную задачу
+  #[serde(default)]
+  pub max_time: Option<String>,
+
+  // Не чаще одного кадра за столько миллисекунд, когда задачи завершаются пачкой
+  // (по умолчанию 30); после паузы кадр рисуется сразу
+  #[serde(default)]
+  pub redraw_interval_ms: Option<u64>,
+
+  // Панель статистики команд под списком задач, по умолчанию true
+  #[serde(default)]
+  pub show_stats: Option<bool>,
+
+  // Порядок задач в списке: "status" (по умолчанию), "duration", "file" или "none"
+  #[serde(default)]
+  pub task_sort: Option<TaskSort>,
+
+  // Группировка списка задач: "group" (по умолчанию) или "file", в TUI переключается клавишей g
+  #[serde(default)]
+  pub group_by: Option<GroupBy>,
+
+  // Сортировка панели статистики команд: "name" (по умолчанию), "total", "avg", "count"
+  #[serde(default)]
+  pub stats_sort: Option<StatsSort>,
+
+  // Направление сортировки: "asc" или "desc",
+  // по умолчанию "asc" для "name" и "desc" для остальных
+  #[serde(default)]
+  pub stats_order: Option<SortOrder>,
+
+  // Значки статусов: "unicode" (по умолчанию), "ascii"
+  // или таблица с заменой отдельных значков, например { preset = "ascii", failed = "FAIL" }
+  #[serde(default)]
+  pub symbols: Option<SymbolsConfig>,
+
+  // Поведение, если для staged файлов нет ни одной команды
+  // "silent" (по умолчанию), "message" или "error"
+  #[serde(default)]
+  pub on_empty: OnEmpty,
+
+  // Поведение, если команды нет в PATH
+  // "error" (по умолчанию), "skip" или "warn"
+  #[serde(default)]
+  pub on_missing_command: OnMissingCommand,
+
+  // Ошибка, если в индексе нет изменений (по умолчанию true),
+  // false - тихий успешный выход, как для `git commit --allow-empty`
+  #[serde(default)]
+  pub fail_on_empty_commit: Option<bool>,
+
+  // Ссылка на JSON Schema для редактора (`fast-staged schema`), при запуске не используется
+  #[serde(default, rename = "$schema")]
+  _schema: Option<String>,
+
+  // Команды для отдельных файлов по точному пути, важнее паттернов групп
+  #[serde(default)]
+  overrides: HashMap<String, OverrideConfig>,
+
+  // Именованные команды, на которые можно ссылаться из паттернов как `@name`
+  #[serde(default)]
+  commands: HashMap<String, CommandSpec>,
+
   // Группы с паттернами и командами
   // Используем HashMap для динамических ключей групп
+  // Паттерны группы "default" проверяются только для файлов,
+  // не попавших ни в одну другую группу
   #[serde(flatten)]
   groups: HashMap<String, GroupConfig>,
+
+  // Паттерны из `.fast-stagedignore` рядом с конфигом: такие файлы не попадают ни в одну группу
+  #[serde(skip)]
+  pub ignore: Vec<String>,
+}
+
+/// Commands for one exact path, as written in the `overrides` table.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OverrideConfig {
+  // Список команд или таблица `fix`/`check`, как у паттернов
+  commands: PatternCommands,
+
+  // true - команды добавляются к командам группы, false (по умолчанию) - заменяют их
+  #[serde(default)]
+  append: bool,
+}
+
+/// Resolved commands for an overridden file.
+#[derive(Debug, Clone)]
+pub struct FileOverride {
+  pub commands: CommandList,
+  pub append: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -57,75 +725,429 @@ pub struct GroupConfig {
   #[serde(default)]
   timeout: Option<String>,
 
+  // Пауза между SIGTERM и SIGKILL для группы (по умолчанию 2s)
+  #[serde(default)]
+  kill_grace: Option<String>,
+
   // Порядок выполнения команд в группе
   // "parallel" (по умолчанию) или "sequential"
   #[serde(default)]
   execution_order: Option<ExecutionOrder>,
 
-  // Паттерны и команды для группы
-  patterns: HashMap<FilePattern, CommandList>,
+  // Метка взаимного исключения: группы с одинаковой меткой
+  // выполняются строго друг за другом
+  #[serde(default)]
+  mutex: Option<String>,
+
+  // Запускать команды только для файлов с измененными строками
+  // и передавать диапазоны строк через {ranges}
+  #[serde(default)]
+  only_changed_lines: bool,
+
+  // Переменные окружения для команд группы, значения поддерживают ${VAR}
+  #[serde(default)]
+  env: HashMap<String, String>,
+
+  // Не наследовать окружение: команда видит только PATH, перечисленные
+  // переменные (или ничего больше при true) и `env` группы. Одинаково локально и в CI
+  #[serde(default)]
+  clean_env: Option<CleanEnv>,
+
+  // Регулярное выражение для stdout: успешная команда с совпадением
+  // считается завершенной с предупреждениями
+  #[serde(default)]
+  warning_pattern: Option<String>,
+
+  // Провалить успешную команду по ее выводу (stdout или stderr): регулярное выражение
+  // или true - любой непустой вывод. Для линтеров, которые печатают предупреждения с кодом 0
+  #[serde(default)]
+  fail_on_output: Option<FailOnOutput>,
+
+  // Регулярные выражения для строк вывода, которые выбрасываются до показа и отчета:
+  // баннеры об устаревании, индикаторы прогресса
+  #[serde(default)]
+  filter_output: Vec<String>,
+
+  // Регулярное выражение для пути файла: именованные группы доступны в командах,
+  // например "^packages/(?<pkg>[^/]+)/" и "{pkg}"
+  #[serde(default)]
+  capture: Option<String>,
+
+  // В последовательной группе после первой ошибки
+  // оставшиеся команды не запускаются
+  #[serde(default)]
+  stop_on_error: bool,
+
+  // В параллельной группе первая ошибка отменяет остальные задачи этой группы,
+  // другие группы продолжают работу
+  #[serde(default)]
+  group_fail_fast: bool,
+
+  // Лимит хранимого вывода команд группы в килобайтах
+  #[serde(default)]
+  output_limit_kb: Option<usize>,
+
+  // Порядок запуска групп: сначала группы с меньшим значением,
+  // группы с одинаковым значением выполняются одновременно
+  #[serde(default)]
+  priority: i32,
+
+  // "file" (по умолчанию) - команда на каждый файл,
+  // "group" - один запуск на группу, если совпал хотя бы один файл
+  #[serde(default)]
+  scope: Scope,
+
+  // Запускать команды и для бинарных файлов (картинки, архивы),
+  // по умолчанию такие файлы пропускаются
+  #[serde(default)]
+  binary: bool,
+
+  // Запускать команды в псевдотерминале: для инструментов,
+  // которые зависают или ведут себя иначе без TTY. stdout и stderr при этом общие
+  #[serde(default)]
+  tty: bool,
+
+  // Передавать в {file} и {files} абсолютные пути от корня репозитория,
+  // для команд, которые сами меняют рабочую директорию (монорепозитории)
+  #[serde(default)]
+  absolute_paths: bool,
+
+  // Какие исходы команды повторять: "never" (по умолчанию), "timeout",
+  // "failure" или "any"; "timeout" не перезапускает детерминированные ошибки линтера
+  #[serde(default)]
+  retry_on: RetryOn,
+
+  // Сколько раз повторять команду при `retry_on`, по умолчанию 1
+  #[serde(default)]
+  retries: Option<usize>,
+
+  // По чему считать `timeout`: "wall" (по умолчанию) или "cpu" - процессорное
+  // время команды и ее потомков
+  #[serde(default)]
+  timeout_clock: TimeoutClock,
+
+  // Сколько упавших задач группе прощается: они видны в отчете, но код выхода
+  // становится ошибкой, только когда их больше. Для постепенного включения строгих линтеров
+  #[serde(default)]
+  max_failures: usize,
+
+  // Провалить задачу, если команда изменила свои файлы: для `check` списков,
+  // где форматтер должен только проверять ("files would be reformatted")
+  // В параллельной группе правку увидят все команды того же файла, надежнее "sequential"
+  #[serde(default)]
+  fail_on_changes: bool,
+
+  // Приоритет команд группы, важнее общего `nice`
+  #[serde(default)]
+  nice: Option<i32>,
+
+  // Обертка команд группы вместо общей, `[]` отключает общую
+  #[serde(default)]
+  command_prefix: Option<Vec<String>>,
+
+  // Команда-условие: группа запускается, только если она завершилась успешно,
+  // иначе задачи группы пропускаются (например, "docker info")
+  #[serde(default)]
+  when: Option<String>,
+
+  // Паттерны и команды для группы: список команд
+  // или таблица с отдельными списками `fix` и `check`
+  patterns: HashMap<FilePattern, PatternCommands>,
+}
+
+/// Finds glob patterns defined in more than one group.
+///
+/// A file goes to the first matching pattern only, so all but one of the groups
+/// never run their commands for it. Returns the pattern with the groups, sorted by name.
+pub fn duplicate_patterns(groups: &[Group]) -> Vec<(String, Vec<String>)> {
+  let mut by_pattern: HashMap<&str, Vec<String>> = HashMap::new();
+
+  // Паттерны группы "default" проверяются последними, пересечение с ними не конфликт
+  for group in groups.iter().filter(|group| group.name != DEFAULT_GROUP) {
+    for pattern in group.patterns.keys() {
+      by_pattern
+        .entry(pattern.as_str())
+        .or_default()
+        .push(group.name.clone());
+    }
+  }
+
+  let mut duplicates: Vec<(String, Vec<String>)> = by_pattern
+    .into_iter()
+    .filter(|(_, groups)| groups.len() > 1)
+    .map(|(pattern, mut groups)| {
+      groups.sort();
+      (pattern.to_string(), groups)
+    })
+    .collect();
+
+  duplicates.sort();
+
+  duplicates
 }
 
 impl Config {
-  pub fn parse_groups(&self) -> Vec<Group> {
+  /// Builds groups with the command lists selected for `mode`.
+  pub fn parse_groups(&self, mode: Mode) -> Result<Vec<Group>> {
     let mut groups = Vec::new();
 
     for (group_name, group_config) in &self.groups {
       groups.push(Group {
         name: group_name.clone(),
-        patterns: group_config.patterns.clone(),
+        patterns: self.resolve_patterns(group_name, &group_config.patterns, mode)?,
         timeout: group_config.timeout.clone().or(self.timeout.clone()),
+        kill_grace: group_config.kill_grace.clone().or(self.kill_grace.clone()),
         execution_order: group_config
           .execution_order
           .unwrap_or(self.execution_order.unwrap_or(ExecutionOrder::Parallel)),
+        mutex: group_config.mutex.clone(),
+        only_changed_lines: group_config.only_changed_lines,
+        env: group_config
+          .env
+          .iter()
+          .map(|(key, value)| (key.clone(), expand_env(value)))
+          .collect(),
+        clean_env: group_config
+          .clean_env
+          .as_ref()
+          .and_then(CleanEnv::allowlist),
+        warning_pattern: group_config
+          .warning_pattern
+          .as_deref()
+          .map(Regex::new)
+          .transpose()
+          .map_err(|e| AppError::InvalidWarningPattern {
+            group: group_name.clone(),
+            details: e.to_string(),
+          })?,
+        fail_on_output: group_config
+          .fail_on_output
+          .as_ref()
+          .and_then(FailOnOutput::pattern)
+          .map(Regex::new)
+          .transpose()
+          .map_err(|e| AppError::InvalidFailOnOutput {
+            group: group_name.clone(),
+            details: e.to_string(),
+          })?,
+        filter_output: group_config
+          .filter_output
+          .iter()
+          .map(|pattern| Regex::new(pattern))
+          .collect::<std::result::Result<_, _>>()
+          .map_err(|e| AppError::InvalidFilterOutput {
+            group: group_name.clone(),
+            details: e.to_string(),
+          })?,
+        capture: group_config
+          .capture
+          .as_deref()
+          .map(Regex::new)
+          .transpose()
+          .map_err(|e| AppError::InvalidCapturePattern {
+            group: group_name.clone(),
+            details: e.to_string(),
+          })?,
+        stop_on_error: group_config.stop_on_error,
+        group_fail_fast: group_config.group_fail_fast,
+        output_limit_kb: group_config.output_limit_kb.or(self.output_limit_kb),
+        priority: group_config.priority,
+        scope: group_config.scope,
+        binary: group_config.binary,
+        tty: group_config.tty,
+        absolute_paths: group_config.absolute_paths,
+        retry_on: group_config.retry_on,
+        retries: group_config.retries.unwrap_or(DEFAULT_RETRIES),
+        timeout_clock: group_config.timeout_clock,
+        max_failures: group_config.max_failures,
+        fail_on_changes: group_config.fail_on_changes,
+        nice: group_config.nice.or(self.nice),
+        command_prefix: group_config
+          .command_prefix
+          .clone()
+          .or_else(|| self.command_prefix.clone())
+          .unwrap_or_default(),
+        when: group_config.when.clone(),
       });
     }
 
-    groups
+    Ok(groups)
+  }
+
+  /// Resolves `overrides` for `mode`, keyed by path.
+  pub fn parse_overrides(&self, mode: Mode) -> Result<HashMap<String, FileOverride>> {
+    let mut overrides = HashMap::new();
+
+    for (path, file_override) in &self.overrides {
+      let commands = file_override
+        .commands
+        .for_mode(mode)
+        .iter()
+        .map(|command| self.resolve_command(OVERRIDES_GROUP, command))
+        .collect::<Result<CommandList>>()?;
+
+      overrides.insert(
+        path.clone(),
+        FileOverride {
+          commands,
+          append: file_override.append,
+        },
+      );
+    }
+
+    Ok(overrides)
+  }
+
+  /// Settings for overridden files outside every group: the top-level defaults.
+  pub fn overrides_group(&self) -> Group {
+    Group {
+      name: OVERRIDES_GROUP.to_string(),
+      patterns: HashMap::new(),
+      timeout: self.timeout.clone(),
+      kill_grace: self.kill_grace.clone(),
+      execution_order: self.execution_order.unwrap_or(ExecutionOrder::Parallel),
+      mutex: None,
+      only_changed_lines: false,
+      env: HashMap::new(),
+      clean_env: None,
+      warning_pattern: None,
+      fail_on_output: None,
+      filter_output: Vec::new(),
+      capture: None,
+      stop_on_error: false,
+      group_fail_fast: false,
+      output_limit_kb: self.output_limit_kb,
+      priority: 0,
+      scope: Scope::File,
+      binary: false,
+      tty: false,
+      absolute_paths: false,
+      retry_on: RetryOn::Never,
+      retries: DEFAULT_RETRIES,
+      timeout_clock: TimeoutClock::Wall,
+      max_failures: 0,
+      fail_on_changes: false,
+      nice: self.nice,
+      command_prefix: self.command_prefix.clone().unwrap_or_default(),
+      when: None,
+    }
   }
 
-  pub fn find_file() -> Result<ConfigSource> {
-    let current_dir = std::env::current_dir()?;
+  fn resolve_patterns(
+    &self,
+    group_name: &str,
+    patterns: &HashMap<FilePattern, PatternCommands>,
+    mode: Mode,
+  ) -> Result<HashMap<FilePattern, CommandList>> {
+    let mut resolved = HashMap::new();
+
+    for (pattern, commands) in patterns {
+      let commands = commands
+        .for_mode(mode)
+        .iter()
+        .map(|command| self.resolve_command(group_name, command))
+        .collect::<Result<CommandList>>()?;
+
+      // `*.{js,ts}` превращается в отдельные паттерны с общими командами
+      for pattern in expand_braces(pattern) {
+        let existing: &mut CommandList = resolved.entry(pattern).or_default();
+
+        for command in &commands {
+          let line = command.to_command_line();
+
+          if !existing.iter().any(|spec| spec.to_command_line() == line) {
+            existing.push(command.clone());
+          }
+        }
+      }
+    }
+
+    Ok(resolved)
+  }
+
+  /// Replaces `@name` references in command lists with commands from the `[commands]` table.
+  fn resolve_command(&self, group_name: &str, command: &CommandSpec) -> Result<CommandSpec> {
+    match command {
+      CommandSpec::Shell(line) if let Some(alias) = line.strip_prefix('@') => self
+        .commands
+        .get(alias)
+        .cloned()
+        .ok_or_else(|| AppError::UnknownCommandAlias {
+          alias: alias.to_string(),
+          group: group_name.to_string(),
+        }),
+      CommandSpec::Detailed {
+        run,
+        continue_on_error,
+        stdin,
+        success_codes,
+      } => Ok(CommandSpec::Detailed {
+        run: Box::new(self.resolve_command(group_name, run)?),
+        continue_on_error: *continue_on_error,
+        stdin: *stdin,
+        success_codes: success_codes.clone(),
+      }),
+      CommandSpec::Chain { chain } => Ok(CommandSpec::Chain {
+        chain: chain
+          .iter()
+          .map(|step| self.resolve_command(group_name, step))
+          .collect::<Result<Vec<_>>>()?,
+      }),
+      _ => Ok(command.clone()),
+    }
+  }
+
+  /// Finds the config in `dir`, or in `FAST_STAGED_CONFIG`.
+  pub fn find_file(dir: &Path) -> Result<ConfigSource> {
+    // Конфиг из окружения важнее любых файлов
+    if let Ok(content) = std::env::var(CONFIG_ENV) {
+      return Ok(ConfigSource::Env(content));
+    }
+
     let mut checked_paths = Vec::new();
-    // Порядок проверки файлов
-    let candidates: Vec<(&str, fn(PathBuf) -> ConfigSource)> = vec![
-      (".fast-staged.toml", ConfigSource::TomlFile),
-      ("fast-staged.toml", ConfigSource::TomlFile),
-      (".fast-staged.json", ConfigSource::JsonFile),
-      ("fast-staged.json", ConfigSource::JsonFile),
-      ("package.json", ConfigSource::PackageJson),
-    ];
-
-    for (filename, source_fn) in candidates {
-      let path = current_dir.join(filename);
+
+    for (filename, source_fn) in CONFIG_FILES {
+      let path = dir.join(filename);
 
       checked_paths.push(path.clone());
 
-      if path.exists() {
-        return Ok(source_fn(path));
+      if !path.exists() {
+        continue;
+      }
+
+      let source = source_fn(path);
+
+      // Манифесты пакетных менеджеров подходят только при наличии секции fast-staged
+      if Self::manifest_has_section(&source) {
+        return Ok(source);
       }
     }
 
     Err(AppError::ConfigNotFound { checked_paths })
   }
 
-  pub fn load() -> Result<Config> {
-    let source = Self::find_file()?;
+  /// Loads the config found in `dir` together with the ignore file next to it.
+  pub fn load(dir: &Path) -> Result<Config> {
+    let source = Self::find_file(dir)?;
+    let ignore_path = match source.path() {
+      Some(path) => path.with_file_name(IGNORE_FILE),
+      None => dir.join(IGNORE_FILE),
+    };
 
-    match source {
+    let mut config = match source {
       ConfigSource::TomlFile(path) => {
         let config_content = fs::read_to_string(&path).map_err(|e| AppError::ConfigInvalid {
           path: path.clone(),
           details: format!("Failed to read toml file: {}", e),
         })?;
 
-        let config: Config =
+        let value: toml::Value =
           toml::from_str(&config_content).map_err(|e| AppError::ConfigInvalid {
             path: path.clone(),
             details: format!("Invalid TOML: {}", e),
           })?;
 
-        Ok(config)
+        Self::from_toml_value(&path, value, "Invalid config")
       }
       ConfigSource::JsonFile(path) => {
         let config_content = fs::read_to_string(&path).map_err(|e| AppError::ConfigInvalid {
@@ -133,42 +1155,213 @@ impl Config {
           details: format!("Failed to read json file: {}", e),
         })?;
 
-        let config: Config =
+        let value: Value =
           serde_json::from_str(&config_content).map_err(|e| AppError::ConfigInvalid {
             path: path.clone(),
             details: format!("Invalid JSON: {}", e),
           })?;
 
-        Ok(config)
+        Self::from_value(&path, value, "Invalid config")
       }
       ConfigSource::PackageJson(path) => Self::load_from_package_json(&path),
+      ConfigSource::CargoToml(path) => Self::load_from_cargo_toml(&path),
+      ConfigSource::DenoJson(path) => Self::load_from_json_manifest(&path, "deno.json"),
+      ConfigSource::Env(content) => Self::load_from_env(&content),
+    }?;
+
+    config.ignore = Self::load_ignore_file(&ignore_path)?;
+
+    Ok(config)
+  }
+
+  /// Reads glob patterns from an ignore file, skipping blank lines and `#` comments.
+  ///
+  /// Entries follow `.gitignore` rules, see [`ignore_globs`].
+  ///
+  /// A missing file means nothing is ignored.
+  fn load_ignore_file(path: &Path) -> Result<Vec<String>> {
+    if !path.exists() {
+      return Ok(Vec::new());
     }
+
+    let content = fs::read_to_string(path).map_err(|e| AppError::ConfigInvalid {
+      path: path.to_path_buf(),
+      details: format!("Failed to read ignore file: {}", e),
+    })?;
+
+    Ok(
+      content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .flat_map(ignore_globs)
+        .collect(),
+    )
+  }
+
+  /// Parses the config passed in `FAST_STAGED_CONFIG`.
+  ///
+  /// Without `FAST_STAGED_CONFIG_FORMAT` content starting with `{` is JSON, anything else TOML.
+  fn load_from_env(content: &str) -> Result<Config> {
+    // Для сообщений об ошибках вместо пути к файлу
+    let path = PathBuf::from(format!("${}", CONFIG_ENV));
+    let format = std::env::var(CONFIG_FORMAT_ENV).ok();
+
+    Self::from_content(&path, content, format.as_deref())
+  }
+
+  /// Parses config text in `format`, or detected from the content when it is `None`.
+  ///
+  /// `path` is only used in error messages.
+  pub fn from_content(path: &Path, content: &str, format: Option<&str>) -> Result<Config> {
+    let path = path.to_path_buf();
+    let format = match format {
+      Some(format) => format.to_lowercase(),
+      None if content.trim_start().starts_with('{') => "json".to_string(),
+      None => "toml".to_string(),
+    };
+
+    match format.as_str() {
+      "toml" => {
+        let value: toml::Value = toml::from_str(content).map_err(|e| AppError::ConfigInvalid {
+          path: path.clone(),
+          details: format!("Invalid TOML: {}", e),
+        })?;
+
+        Self::from_toml_value(&path, value, "Invalid config")
+      }
+      "json" => {
+        let value: Value = serde_json::from_str(content).map_err(|e| AppError::ConfigInvalid {
+          path: path.clone(),
+          details: format!("Invalid JSON: {}", e),
+        })?;
+
+        Self::from_value(&path, value, "Invalid config")
+      }
+      other => Err(AppError::ConfigInvalid {
+        path,
+        details: format!(
+          "Unknown config format '{}', expected \"toml\" or \"json\"",
+          other
+        ),
+      }),
+    }
+  }
+
+  /// Checks that a manifest source contains a `fast-staged` section.
+  ///
+  /// Dedicated config files always qualify.
+  fn manifest_has_section(source: &ConfigSource) -> bool {
+    match source {
+      ConfigSource::TomlFile(_) | ConfigSource::JsonFile(_) | ConfigSource::Env(_) => true,
+      ConfigSource::PackageJson(path) | ConfigSource::DenoJson(path) => fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+        .is_some_and(|json| json.get("fast-staged").is_some()),
+      ConfigSource::CargoToml(path) => fs::read_to_string(path)
+        .ok()
+        .and_then(|content| toml::from_str::<toml::Value>(&content).ok())
+        .is_some_and(|manifest| Self::cargo_metadata_section(&manifest).is_some()),
+    }
+  }
+
+  fn cargo_metadata_section(manifest: &toml::Value) -> Option<&toml::Value> {
+    manifest
+      .get("package")
+      .or_else(|| manifest.get("workspace"))
+      .and_then(|table| table.get("metadata"))
+      .and_then(|metadata| metadata.get("fast-staged"))
   }
 
   pub fn load_from_package_json(path: &Path) -> Result<Config> {
+    Self::load_from_json_manifest(path, "package.json")
+  }
+
+  /// Loads the config from the `fast-staged` key of a JSON manifest (`package.json`, `deno.json`).
+  pub fn load_from_json_manifest(path: &Path, manifest_name: &str) -> Result<Config> {
     let content = fs::read_to_string(path).map_err(|e| AppError::ConfigInvalid {
       path: path.to_path_buf(),
-      details: format!("Failed to read package.json: {}", e),
+      details: format!("Failed to read {}: {}", manifest_name, e),
     })?;
 
     let json: Value = serde_json::from_str(&content).map_err(|e| AppError::ConfigInvalid {
       path: path.to_path_buf(),
-      details: format!("Invalid JSON in package.json: {}", e),
+      details: format!("Invalid JSON in {}: {}", manifest_name, e),
     })?;
 
     let fast_staged = json
       .get("fast-staged")
       .ok_or_else(|| AppError::ConfigInvalid {
         path: path.to_path_buf(),
-        details: "No 'fast-staged' section found in package.json".to_string(),
+        details: format!("No 'fast-staged' section found in {}", manifest_name),
       })?;
 
-    let config: Config =
-      serde_json::from_value(fast_staged.clone()).map_err(|e| AppError::ConfigInvalid {
+    Self::from_value(path, fast_staged.clone(), "Invalid 'fast-staged' section")
+  }
+
+  /// Loads the config from `[package.metadata.fast-staged]`
+  /// (or `[workspace.metadata.fast-staged]`) of a `Cargo.toml`.
+  pub fn load_from_cargo_toml(path: &Path) -> Result<Config> {
+    let content = fs::read_to_string(path).map_err(|e| AppError::ConfigInvalid {
+      path: path.to_path_buf(),
+      details: format!("Failed to read Cargo.toml: {}", e),
+    })?;
+
+    let manifest: toml::Value = toml::from_str(&content).map_err(|e| AppError::ConfigInvalid {
+      path: path.to_path_buf(),
+      details: format!("Invalid TOML in Cargo.toml: {}", e),
+    })?;
+
+    let fast_staged =
+      Self::cargo_metadata_section(&manifest).ok_or_else(|| AppError::ConfigInvalid {
         path: path.to_path_buf(),
-        details: format!("Invalid 'fast-staged' section: {}", e),
+        details: "No '[package.metadata.fast-staged]' section found in Cargo.toml".to_string(),
       })?;
 
-    Ok(config)
+    Self::from_toml_value(path, fast_staged.clone(), "Invalid 'fast-staged' section")
+  }
+
+  fn from_toml_value(path: &Path, value: toml::Value, context: &str) -> Result<Config> {
+    let value = serde_json::to_value(value).map_err(|e| AppError::ConfigInvalid {
+      path: path.to_path_buf(),
+      details: format!("{}: {}", context, e),
+    })?;
+
+    Self::from_value(path, value, context)
   }
+
+  /// Validates a raw config and deserializes it.
+  fn from_value(path: &Path, value: Value, context: &str) -> Result<Config> {
+    check_config(&value).map_err(|details| AppError::ConfigInvalid {
+      path: path.to_path_buf(),
+      details: format!("{}: {}", context, details),
+    })?;
+
+    serde_json::from_value(value).map_err(|e| AppError::ConfigInvalid {
+      path: path.to_path_buf(),
+      details: format!("{}: {}", context, e),
+    })
+  }
+}
+
+/// Expands `${VAR}` references with values from the process environment.
+///
+/// Unset variables expand to an empty string, an unterminated `${` is kept as is.
+pub fn expand_env(value: &str) -> String {
+  let mut expanded = String::with_capacity(value.len());
+  let mut rest = value;
+
+  while let Some(start) = rest.find("${") {
+    let Some(len) = rest[start + 2..].find('}') else {
+      break;
+    };
+
+    expanded.push_str(&rest[..start]);
+    let name = &rest[start + 2..start + 2 + len];
+    expanded.push_str(&std::env::var(name).unwrap_or_default());
+    rest = &rest[start + 3 + len..];
+  }
+
+  expanded.push_str(rest);
+  expanded
 }
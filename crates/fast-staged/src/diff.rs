@@ -0,0 +1,94 @@
+use crate::app::AppError;
+use crate::app::Result;
+use crate::file::head_tree_id;
+use gix::diff::blob::intern::InternedInput;
+use gix::diff::blob::{Algorithm, diff};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+
+/// An inclusive, 1-based range of lines added or changed in the index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRange {
+  pub start: u32,
+  pub end: u32,
+}
+
+impl std::fmt::Display for LineRange {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}:{}", self.start, self.end)
+  }
+}
+
+/// Computes the changed line ranges of every staged file of the repository at `root`
+/// relative to `HEAD`.
+///
+/// Files that don't exist in `HEAD` (new files, first commit) are reported as changed in full.
+pub async fn staged_line_ranges(
+  root: &Path,
+  files: Vec<String>,
+) -> Result<HashMap<String, Vec<LineRange>>> {
+  let root = root.to_path_buf();
+  let ranges = tokio::task::spawn_blocking(move || -> Result<HashMap<String, Vec<LineRange>>> {
+    let repo = gix::open(&root).map_err(|_| AppError::NotGitRepository { dir: root })?;
+
+    let index = repo
+      .index()
+      .map_err(|e| AppError::GitError(format!("{}", e)))?;
+
+    // У нового репозитория HEAD еще не указывает на коммит: все файлы новые
+    let head_tree = head_tree_id(&repo)?
+      .map(|id| repo.find_tree(id))
+      .transpose()
+      .map_err(|e| AppError::GitError(format!("{}", e)))?;
+    let mut ranges = HashMap::new();
+
+    for file in files {
+      let Some(entry) = index.entry_by_path(file.as_str().into()) else {
+        continue;
+      };
+
+      let new = repo
+        .find_object(entry.id)
+        .map_err(|e| AppError::GitError(format!("{}", e)))?
+        .detach()
+        .data;
+
+      let old = head_tree
+        .as_ref()
+        .and_then(|tree| tree.lookup_entry_by_path(&file).ok().flatten())
+        .and_then(|entry| entry.object().ok())
+        .map(|object| object.detach().data)
+        .unwrap_or_default();
+
+      ranges.insert(file, line_ranges(&old, &new));
+    }
+
+    Ok(ranges)
+  })
+  .await??;
+
+  Ok(ranges)
+}
+
+/// Returns the line ranges of `new` that were added or changed compared to `old`.
+fn line_ranges(old: &[u8], new: &[u8]) -> Vec<LineRange> {
+  let input = InternedInput::new(old, new);
+  let mut ranges = Vec::new();
+
+  diff(
+    Algorithm::Histogram,
+    &input,
+    |_before: Range<u32>, after: Range<u32>| {
+      // Чистое удаление строк не дает диапазона в новой версии файла
+      if !after.is_empty() {
+        ranges.push(LineRange {
+          start: after.start + 1,
+          end: after.end,
+        });
+      }
+    },
+  );
+
+  ranges
+}
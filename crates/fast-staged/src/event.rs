@@ -1,6 +1,7 @@
 use color_eyre::eyre::OptionExt;
 use futures::{FutureExt, StreamExt};
 use ratatui::crossterm::event::Event as CrosstermEvent;
+use std::io::IsTerminal;
 use std::time::Duration;
 use tokio::sync::mpsc;
 
@@ -47,8 +48,17 @@ pub struct EventHandler {
 impl EventHandler {
   /// Constructs a new instance of [`EventHandler`] and spawns a new thread to handle events.
   pub fn new() -> Self {
+    Self::spawn(true)
+  }
+
+  /// Like [`EventHandler::new`], but never reads terminal events even if stdin is a TTY.
+  pub fn without_terminal() -> Self {
+    Self::spawn(false)
+  }
+
+  fn spawn(read_terminal: bool) -> Self {
     let (sender, receiver) = mpsc::unbounded_channel();
-    let actor = EventTask::new(sender.clone());
+    let actor = EventTask::new(sender.clone(), read_terminal);
     tokio::spawn(async { actor.run().await });
     Self { sender, receiver }
   }
@@ -85,12 +95,17 @@ impl EventHandler {
 struct EventTask {
   /// Event sender channel.
   sender: mpsc::UnboundedSender<Event>,
+  /// Whether to read crossterm events from stdin.
+  read_terminal: bool,
 }
 
 impl EventTask {
   /// Constructs a new instance of [`EventThread`].
-  fn new(sender: mpsc::UnboundedSender<Event>) -> Self {
-    Self { sender }
+  fn new(sender: mpsc::UnboundedSender<Event>, read_terminal: bool) -> Self {
+    Self {
+      sender,
+      read_terminal,
+    }
   }
 
   /// Runs the event thread.
@@ -98,11 +113,19 @@ impl EventTask {
   /// This function emits tick events at a fixed rate and polls for crossterm events in between.
   async fn run(self) -> color_eyre::Result<()> {
     let tick_rate = Duration::from_secs_f64(1.0 / TICK_FPS);
-    let mut reader = crossterm::event::EventStream::new();
+    // Without a terminal (e.g. piped output in CI) there are no crossterm events to read.
+    let mut reader = (self.read_terminal && std::io::stdin().is_terminal())
+      .then(crossterm::event::EventStream::new);
     let mut tick = tokio::time::interval(tick_rate);
     loop {
       let tick_delay = tick.tick();
-      let crossterm_event = reader.next().fuse();
+      let crossterm_event = async {
+        match reader.as_mut() {
+          Some(reader) => reader.next().await,
+          None => std::future::pending().await,
+        }
+      }
+      .fuse();
       tokio::select! {
         _ = self.sender.closed() => {
           break;
@@ -0,0 +1,86 @@
+use crate::app::{App, AppError};
+use crate::cli::Cli;
+use crate::report::{RepoReport, TaskReport};
+use tokio::task::JoinSet;
+
+/// Runs fast-staged in every repository of `--repo` at once and prints a combined report.
+///
+/// Every repository gets its own headless [`App`] rooted there, with the rest of
+/// the arguments unchanged. Fails when any repository failed, after the report is printed.
+pub async fn run_repos(cli: &Cli) -> color_eyre::Result<()> {
+  let mut runs = JoinSet::new();
+
+  for (idx, repo) in cli.repo.iter().enumerate() {
+    let cli = Cli {
+      repo: vec![repo.clone()],
+      ..cli.clone()
+    };
+
+    runs.spawn(async move { (idx, App::headless(cli).run_repo().await) });
+  }
+
+  let mut reports: Vec<Option<RepoReport>> = vec![None; cli.repo.len()];
+
+  while let Some(result) = runs.join_next().await {
+    let (idx, report) = result?;
+    reports[idx] = Some(report);
+  }
+
+  // Порядок отчета совпадает с порядком `--repo`, а не с тем, кто закончил первым
+  let reports: Vec<RepoReport> = reports.into_iter().flatten().collect();
+
+  if cli.json || cli.ndjson {
+    println!("{}", serde_json::to_string_pretty(&reports)?);
+  } else {
+    print_repo_summary(&reports);
+  }
+
+  let failed: Vec<String> = reports
+    .iter()
+    .filter(|report| report.error.is_some())
+    .map(|report| report.repo.clone())
+    .collect();
+
+  if !failed.is_empty() {
+    return Err(AppError::ReposFailed { repos: failed }.into());
+  }
+
+  Ok(())
+}
+
+fn print_repo_summary(reports: &[RepoReport]) {
+  for report in reports {
+    if let (Some(error), true) = (&report.error, report.tasks.is_empty()) {
+      println!("{}: error", report.repo);
+
+      for line in error.lines() {
+        println!("  {}", line);
+      }
+
+      continue;
+    }
+
+    let failed: Vec<&TaskReport> = report.tasks.iter().filter(|task| task.failed).collect();
+
+    println!(
+      "{}: {} task(s), {} failed",
+      report.repo,
+      report.tasks.len(),
+      failed.len()
+    );
+
+    // Ошибка без упавших задач (например, не удался откат) иначе не видна
+    let unexplained = failed.is_empty();
+
+    for task in failed {
+      match &task.error {
+        Some(error) => println!("  {}: {} ({})", task.filename, task.command, error),
+        None => println!("  {}: {} ({})", task.filename, task.command, task.status),
+      }
+    }
+
+    if let (Some(error), true) = (&report.error, unexplained) {
+      println!("  error: {}", error);
+    }
+  }
+}
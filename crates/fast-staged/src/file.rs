@@ -1,53 +1,486 @@
 use crate::app::AppError;
 use crate::app::Result;
+use crate::config::CommandSpec;
 use crate::config::Config;
+use crate::config::DEFAULT_GROUP;
 use crate::config::ExecutionOrder;
-use fast_glob::glob_match;
+use crate::config::Group;
+use crate::config::Mode;
+use crate::config::RenamePaths;
+use crate::config::Scope;
+use crate::config::StdinMode;
+use crate::config::{RetryOn, TimeoutClock};
+use crate::diff::{LineRange, staged_line_ranges};
+use crate::lock::STATE_DIR;
+use crate::matcher::PatternMatcher;
+use regex::Regex;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// How often (in index entries) file discovery reports progress.
+const PROGRESS_EVERY: usize = 256;
+
+/// Indexes with fewer entries are matched after they are read, without a pipeline.
+const STREAM_MATCH_THRESHOLD: usize = 4096;
+
+/// How many paths go to the matcher thread at once.
+const STREAM_CHUNK: usize = 512;
+
+/// How many leading bytes of a blob are checked for NUL, the same heuristic git uses.
+const BINARY_CHECK_BYTES: usize = 8000;
 
 #[derive(Debug, Clone)]
 pub struct FileCommand {
+  // Для `scope = "group"` здесь подпись вида "3 file(s)", а сами файлы в `files`
   pub filename: String,
+  // Один список на все команды группы с тем же набором файлов
+  pub files: Arc<Vec<String>>,
   pub command: String,
+  // Аргументы для запуска без shell, если команда задана списком
+  pub argv: Option<Vec<String>>,
   pub group_name: String,
   pub timeout: Option<String>,
+  pub kill_grace: Option<String>,
   pub execution_order: ExecutionOrder,
+  pub mutex: Option<String>,
+  pub only_changed_lines: bool,
+  pub line_ranges: Option<Vec<LineRange>>,
+  pub env: HashMap<String, String>,
+  pub warning_pattern: Option<Regex>,
+  pub clean_env: Option<Vec<String>>,
+  pub fail_on_output: Option<Regex>,
+  pub filter_output: Vec<Regex>,
+  pub stop_on_error: bool,
+  pub group_fail_fast: bool,
+  pub continue_on_error: bool,
+  pub stdin: StdinMode,
+  pub success_codes: Vec<i32>,
+  pub output_limit_kb: Option<usize>,
+  // Группы с меньшим приоритетом завершаются раньше, чем стартуют следующие
+  pub priority: i32,
+  // Группа разрешает запуск для бинарных файлов
+  pub binary: bool,
+  // Запуск в псевдотерминале вместо каналов
+  pub tty: bool,
+  pub absolute_paths: bool,
+  // Какие исходы повторять и сколько раз
+  pub retry_on: RetryOn,
+  pub retries: usize,
+  pub timeout_clock: TimeoutClock,
+  // Команда не должна менять файлы, иначе задача проваливается
+  pub fail_on_changes: bool,
+  pub nice: Option<i32>,
+  // Обертка, которая запускает команду (`nix develop -c`), пусто - без нее
+  pub command_prefix: Vec<String>,
+  // Условие запуска группы
+  pub when: Option<String>,
+  // Общая метка шагов одной цепочки (`chain`), шаги выполняются по очереди
+  pub chain: Option<String>,
+  // Команда уже проходила для этого содержимого файла (`--since-last-run`)
+  pub cached: bool,
+  // Команда взята из `overrides` для этого файла, а не из паттерна группы
+  pub from_override: bool,
 }
 
 impl FileCommand {
-  pub fn command_exists(&self) -> bool {
+  fn new(group: &Group, spec: &CommandSpec, filename: String, files: Arc<Vec<String>>) -> Self {
+    FileCommand {
+      filename,
+      files,
+      command: spec.to_command_line(),
+      argv: spec.argv(),
+      group_name: group.name.clone(),
+      timeout: group.timeout.clone(),
+      kill_grace: group.kill_grace.clone(),
+      execution_order: group.execution_order,
+      mutex: group.mutex.clone(),
+      only_changed_lines: group.only_changed_lines,
+      line_ranges: None,
+      env: group.env.clone(),
+      warning_pattern: group.warning_pattern.clone(),
+      clean_env: group.clean_env.clone(),
+      fail_on_output: group.fail_on_output.clone(),
+      filter_output: group.filter_output.clone(),
+      stop_on_error: group.stop_on_error,
+      group_fail_fast: group.group_fail_fast,
+      continue_on_error: spec.continue_on_error(),
+      stdin: spec.stdin(),
+      success_codes: spec.success_codes(),
+      output_limit_kb: group.output_limit_kb,
+      priority: group.priority,
+      binary: group.binary,
+      tty: group.tty,
+      absolute_paths: group.absolute_paths,
+      retry_on: group.retry_on,
+      retries: group.retries,
+      timeout_clock: group.timeout_clock,
+      fail_on_changes: group.fail_on_changes,
+      nice: group.nice,
+      command_prefix: group.command_prefix.clone(),
+      when: group.when.clone(),
+      chain: None,
+      cached: false,
+      from_override: false,
+    }
+  }
+
+  /// Builds one command per step of `spec`, tying chain steps together.
+  ///
+  /// A plain command that already runs for this file is skipped. Chain steps are
+  /// never dropped, since later steps rely on them, so a chain step that repeats
+  /// another command for the file is an error.
+  fn from_steps(
+    group: &Group,
+    spec: &CommandSpec,
+    filename: String,
+    files: Arc<Vec<String>>,
+    seen: &mut HashMap<(String, String), bool>,
+  ) -> Result<Vec<Self>> {
+    let in_chain = spec.is_chain();
+    let chain = spec
+      .is_chain()
+      .then(|| format!("{}: {}", filename, spec.to_command_line()));
+    // Подстановки из пути есть только у команд на отдельный файл
+    let captures = if files.is_empty() {
+      path_captures(group, &filename)
+    } else {
+      Vec::new()
+    };
+
+    let mut file_commands = Vec::new();
+
+    for step in spec.steps() {
+      let command = step.to_command_line();
+
+      // Значение - был ли уже добавленный шаг частью цепочки
+      match seen.entry((filename.clone(), command.clone())) {
+        Entry::Occupied(entry) if !in_chain && !*entry.get() => continue,
+        Entry::Occupied(_) => {
+          return Err(AppError::DuplicateChainStep {
+            file: filename,
+            command,
+          });
+        }
+        Entry::Vacant(entry) => {
+          entry.insert(in_chain);
+        }
+      }
+
+      let mut file_cmd = FileCommand::new(group, step, filename.clone(), files.clone());
+      file_cmd.chain = chain.clone();
+      file_cmd.substitute_captures(&captures);
+      file_commands.push(file_cmd);
+    }
+
+    Ok(file_commands)
+  }
+
+  /// Checks that the program exists in PATH or, for a relative path, under `root`.
+  pub fn command_exists(&self, root: &Path) -> bool {
+    // Сама команда может существовать только внутри обертки (контейнер, nix shell)
+    if let Some(wrapper) = self.command_prefix.first() {
+      return program_exists(wrapper, root);
+    }
+
+    if let Some(argv) = &self.argv {
+      return argv
+        .first()
+        .is_some_and(|program| program_exists(program, root));
+    }
+
     let command = &self.command;
     // Проверяем наличие команды в PATH
     // Для команд вида "sh -c 'command'" проверяем наличие 'sh'
     if command.starts_with("sh -c") {
-      return which::which("sh").is_ok();
+      return program_exists("sh", root);
     }
 
     // Извлекаем первую часть команды (до пробела)
     let first_part = command.split_whitespace().next().unwrap_or(command);
 
-    which::which(first_part).is_ok()
+    program_exists(first_part, root)
   }
 
-  pub async fn get_changed_files() -> Result<Vec<String>> {
-    // Используем gix для получения списка измененных файлов
-    let changed_files = tokio::task::spawn_blocking(|| -> Result<Vec<String>> {
-      let current_dir = std::env::current_dir().map_err(|e| AppError::IoError(e))?;
+  /// Files this command covers: the single file, or every matched file for a group-wide command.
+  pub fn target_files(&self) -> Vec<&str> {
+    if self.files.is_empty() {
+      vec![self.filename.as_str()]
+    } else {
+      self.files.iter().map(String::as_str).collect()
+    }
+  }
 
-      let repo = gix::open(".").map_err(|_| AppError::NotGitRepository {
-        dir: current_dir.clone(),
-      })?;
+  pub fn needs_line_ranges(&self) -> bool {
+    // У команды на всю группу нет одного файла, для которого считать диапазоны
+    self.files.is_empty() && (self.only_changed_lines || self.command.contains("{ranges}"))
+  }
+
+  /// Computes staged line ranges for commands that need them.
+  ///
+  /// In `only_changed_lines` mode commands for files without added or changed lines are dropped.
+  pub async fn attach_line_ranges(root: &Path, file_commands: &mut Vec<FileCommand>) -> Result<()> {
+    let mut files: Vec<String> = file_commands
+      .iter()
+      .filter(|file_cmd| file_cmd.needs_line_ranges())
+      .map(|file_cmd| file_cmd.filename.clone())
+      .collect();
+
+    if files.is_empty() {
+      return Ok(());
+    }
+
+    files.sort();
+    files.dedup();
+
+    let ranges = staged_line_ranges(root, files).await?;
+
+    file_commands.retain_mut(|file_cmd| {
+      if !file_cmd.needs_line_ranges() {
+        return true;
+      }
+
+      let file_ranges = ranges.get(&file_cmd.filename).cloned().unwrap_or_default();
+
+      if file_cmd.only_changed_lines && file_ranges.is_empty() {
+        return false;
+      }
+
+      file_cmd.line_ranges = Some(file_ranges);
+      true
+    });
+
+    Ok(())
+  }
+
+  /// Appends extra arguments to the command, quoting them for `sh -c`.
+  pub fn append_args(&mut self, args: &[String]) {
+    if let Some(argv) = &mut self.argv {
+      argv.extend(args.iter().cloned());
+    }
+
+    for arg in args {
+      self.command.push(' ');
+      self.command.push_str(&shell_quote(arg));
+    }
+  }
+
+  /// Replaces `{name}` with the named groups captured from the path.
+  ///
+  /// The shell command gets quoted values, argv the raw ones.
+  fn substitute_captures(&mut self, captures: &[(String, String)]) {
+    for (name, value) in captures {
+      let placeholder = format!("{{{}}}", name);
+
+      self.command = self.command.replace(&placeholder, &shell_quote(value));
+
+      if let Some(argv) = &mut self.argv {
+        for arg in argv.iter_mut() {
+          *arg = arg.replace(&placeholder, value);
+        }
+      }
+    }
+  }
+
+  /// Finds the worktree root of the repository containing `path`.
+  pub fn repo_root(path: &Path) -> Result<PathBuf> {
+    let repo = gix::discover(path).map_err(|_| AppError::NotGitRepository {
+      dir: path.to_path_buf(),
+    })?;
+
+    // У bare-репозитория нет рабочего дерева со staged файлами
+    repo
+      .workdir()
+      .map(Path::to_path_buf)
+      .ok_or_else(|| AppError::NotGitRepository {
+        dir: path.to_path_buf(),
+      })
+  }
+
+  /// Returns the name of a git operation in progress (merge, rebase, ...), if any.
+  ///
+  /// Running formatters in the middle of such an operation can mangle conflict markers.
+  pub async fn operation_in_progress(root: &Path) -> Result<Option<String>> {
+    let root = root.to_path_buf();
+    let operation = tokio::task::spawn_blocking(move || -> Result<Option<String>> {
+      let repo = gix::open(&root).map_err(|_| AppError::NotGitRepository { dir: root })?;
+
+      // Bisect не трогает рабочее дерево, поэтому его пропускаем
+      let operation = repo
+        .state()
+        .filter(|state| *state != gix::state::InProgress::Bisect)
+        .map(|state| format!("{:?}", state));
+
+      Ok(operation)
+    })
+    .await??;
+
+    Ok(operation)
+  }
+
+  /// Lists the staged files of the repository at `root`, reporting the number
+  /// found so far through `progress`.
+  pub async fn get_changed_files(
+    root: &Path,
+    progress: Option<watch::Sender<usize>>,
+  ) -> Result<Vec<String>> {
+    let (changed_files, _) = Self::get_changed_files_matched(root, None, progress).await?;
+
+    Ok(changed_files)
+  }
+
+  /// Lists staged files like [`FileCommand::get_changed_files`] and, for a large
+  /// index, matches them against `patterns` on another thread while the index is
+  /// still being read.
+  ///
+  /// Files missing from the returned map are left to the regular matching.
+  pub async fn get_changed_files_matched(
+    root: &Path,
+    patterns: Option<Arc<FilePatterns>>,
+    progress: Option<watch::Sender<usize>>,
+  ) -> Result<(Vec<String>, HashMap<String, FileMatch>)> {
+    let root = root.to_path_buf();
+    // Используем gix для получения списка измененных файлов
+    let changed_files = tokio::task::spawn_blocking(move || -> Result<_> {
+      let repo = gix::open(&root).map_err(|_| AppError::NotGitRepository { dir: root })?;
 
       let index = repo
         .index()
         .map_err(|e| AppError::GitError(format!("{}", e)))?;
 
-      let mut changed_files = Vec::new();
+      let entries = index.entries();
+
+      // На маленьком индексе поток сопоставления стоит дороже, чем экономит
+      let patterns = patterns.filter(|_| entries.len() >= STREAM_MATCH_THRESHOLD);
+      let (chunks, matcher) = match patterns {
+        Some(patterns) => {
+          let (tx, rx) = std::sync::mpsc::channel::<Vec<String>>();
+          let matcher = std::thread::spawn(move || {
+            let mut prematched = HashMap::new();
+
+            for file in rx.into_iter().flatten() {
+              let file_match = patterns.classify(&file);
+              prematched.insert(file, file_match);
+            }
+
+            prematched
+          });
+
+          (Some(tx), Some(matcher))
+        }
+        None => (None, None),
+      };
+
+      let mut changed_files = Vec::with_capacity(entries.len());
+      let mut chunk = Vec::new();
 
       // Получаем файлы из индекса (staged files)
-      for entry in index.entries() {
-        changed_files.push(entry.path(&index).to_string());
+      for (idx, entry) in entries.iter().enumerate() {
+        let path = entry.path(&index).to_string();
+
+        if let Some(chunks) = &chunks {
+          chunk.push(path.clone());
+
+          if chunk.len() == STREAM_CHUNK {
+            // Поток сопоставления живет, пока жив отправитель
+            let _ = chunks.send(std::mem::take(&mut chunk));
+          }
+        }
+
+        changed_files.push(path);
+
+        if let Some(progress) = &progress
+          && idx % PROGRESS_EVERY == 0
+        {
+          progress.send_replace(changed_files.len());
+        }
+      }
+
+      if let Some(chunks) = chunks {
+        let _ = chunks.send(chunk);
+      }
+
+      let prematched = match matcher {
+        Some(matcher) => matcher
+          .join()
+          .map_err(|_| AppError::GitError("file matching thread panicked".to_string()))?,
+        None => HashMap::new(),
+      };
+
+      // При конфликтах слияния один путь встречается в индексе на нескольких стадиях
+      changed_files.dedup();
+
+      if changed_files.is_empty() {
+        return Err(AppError::NoStagedFiles);
+      }
+
+      Ok((changed_files, prematched))
+    })
+    .await??;
+
+    Ok(changed_files)
+  }
+
+  /// Lists files changed in the working tree relative to the index: modified,
+  /// new untracked (not ignored) and renamed ones. Deleted files are left out.
+  pub async fn get_worktree_changes(
+    root: &Path,
+    progress: Option<watch::Sender<usize>>,
+  ) -> Result<Vec<String>> {
+    let root = root.to_path_buf();
+    let changed_files = tokio::task::spawn_blocking(move || -> Result<Vec<String>> {
+      use gix::status::index_worktree::iter::Summary;
+
+      let repo = gix::open(&root).map_err(|_| AppError::NotGitRepository { dir: root })?;
+
+      let items = repo
+        .status(gix::progress::Discard)
+        .map_err(|e| AppError::GitError(format!("{}", e)))?
+        .untracked_files(gix::status::UntrackedFiles::Files)
+        .into_index_worktree_iter(Vec::new())
+        .map_err(|e| AppError::GitError(format!("{}", e)))?;
+
+      let mut changed_files = Vec::new();
+
+      for item in items {
+        let item = item.map_err(|e| AppError::GitError(format!("{}", e)))?;
+
+        // Удаленный файл проверять нечем, конфликт решается до проверки
+        let Some(
+          Summary::Modified
+          | Summary::TypeChange
+          | Summary::Added
+          | Summary::IntentToAdd
+          | Summary::Renamed
+          | Summary::Copied,
+        ) = item.summary()
+        else {
+          continue;
+        };
+
+        let path = item.rela_path().to_string();
+
+        // Свои файлы состояния (блокировка, кеш) не проверяем
+        if path.starts_with(&format!("{}/", STATE_DIR)) {
+          continue;
+        }
+
+        changed_files.push(path);
+
+        if let Some(progress) = &progress
+          && changed_files.len() % PROGRESS_EVERY == 0
+        {
+          progress.send_replace(changed_files.len());
+        }
       }
 
+      changed_files.sort();
+      changed_files.dedup();
+
       if changed_files.is_empty() {
         return Err(AppError::NoStagedFiles);
       }
@@ -59,51 +492,638 @@ impl FileCommand {
     Ok(changed_files)
   }
 
+  /// Finds files renamed in the index relative to `HEAD`, as `(old, new)` pairs.
+  ///
+  /// Renames are detected the way `git status` does, honoring `status.renames` and `diff.renames`.
+  pub async fn staged_renames(root: &Path) -> Result<Vec<(String, String)>> {
+    let root = root.to_path_buf();
+    let renames = tokio::task::spawn_blocking(move || -> Result<Vec<(String, String)>> {
+      let repo = gix::open(&root).map_err(|_| AppError::NotGitRepository { dir: root })?;
+
+      // До первого коммита переименовывать нечего
+      let Some(head_tree) = head_tree_id(&repo)? else {
+        return Ok(Vec::new());
+      };
+
+      let index = repo
+        .index()
+        .map_err(|e| AppError::GitError(format!("{}", e)))?;
+
+      let mut renames = Vec::new();
+
+      repo
+        .tree_index_status(
+          &head_tree,
+          &index,
+          None,
+          gix::status::tree_index::TrackRenames::AsConfigured,
+          |change, _, _| {
+            if let gix::diff::index::ChangeRef::Rewrite {
+              source_location,
+              location,
+              copy: false,
+              ..
+            } = change
+            {
+              renames.push((source_location.to_string(), location.to_string()));
+            }
+
+            Ok::<_, Infallible>(ControlFlow::Continue(()))
+          },
+        )
+        .map_err(|e| AppError::GitError(format!("{}", e)))?;
+
+      Ok(renames)
+    })
+    .await??;
+
+    Ok(renames)
+  }
+
+  /// Swaps or adds the old paths of renamed files according to the `renames` option.
+  pub fn apply_renames(
+    files: Vec<String>,
+    renames: &[(String, String)],
+    paths: RenamePaths,
+  ) -> Vec<String> {
+    let old_paths: HashMap<&str, &str> = renames
+      .iter()
+      .map(|(old, new)| (new.as_str(), old.as_str()))
+      .collect();
+
+    let mut result = Vec::with_capacity(files.len());
+
+    for file in files {
+      match (old_paths.get(file.as_str()), paths) {
+        (Some(old), RenamePaths::Old) => result.push(old.to_string()),
+        (Some(old), RenamePaths::Both) => {
+          result.push(old.to_string());
+          result.push(file);
+        }
+        _ => result.push(file),
+      }
+    }
+
+    result
+  }
+
+  /// Counts distinct matched files per group.
+  pub fn group_file_counts(file_commands: &[FileCommand]) -> HashMap<String, usize> {
+    let mut files: HashMap<&str, HashSet<&str>> = HashMap::new();
+
+    for file_cmd in file_commands {
+      files
+        .entry(file_cmd.group_name.as_str())
+        .or_default()
+        .extend(file_cmd.target_files());
+    }
+
+    files
+      .into_iter()
+      .map(|(group, files)| (group.to_string(), files.len()))
+      .collect()
+  }
+
+  /// Drops files matched by `.gitignore` and the other exclude sources.
+  ///
+  /// Staged files are tracked, but a force-added ignored artifact can still end up in the index.
+  pub async fn drop_ignored(root: &Path, files: Vec<String>) -> Result<Vec<String>> {
+    let root = root.to_path_buf();
+    let kept = tokio::task::spawn_blocking(move || -> Result<Vec<String>> {
+      let repo = gix::open(&root).map_err(|_| AppError::NotGitRepository { dir: root })?;
+
+      let index = repo
+        .index()
+        .map_err(|e| AppError::GitError(format!("{}", e)))?;
+
+      let mut excludes = repo
+        .excludes(&index, None, Default::default())
+        .map_err(|e| AppError::GitError(format!("{}", e)))?;
+
+      let kept = files
+        .into_iter()
+        .filter(|file| {
+          excludes
+            .at_path(file, Some(gix::index::entry::Mode::FILE))
+            .map(|platform| !platform.is_excluded())
+            .unwrap_or(true)
+        })
+        .collect();
+
+      Ok(kept)
+    })
+    .await??;
+
+    Ok(kept)
+  }
+
+  /// Drops commands for binary files unless their group sets `binary = true`.
+  ///
+  /// A file is binary when its staged blob has a NUL byte near the start. Group-wide
+  /// commands are kept, they do not run on single files.
+  pub async fn drop_binary(
+    root: &Path,
+    file_commands: Vec<FileCommand>,
+  ) -> Result<Vec<FileCommand>> {
+    let files: HashSet<String> = file_commands
+      .iter()
+      .filter(|file_cmd| !file_cmd.binary && file_cmd.files.is_empty())
+      .map(|file_cmd| file_cmd.filename.clone())
+      .collect();
+
+    if files.is_empty() {
+      return Ok(file_commands);
+    }
+
+    let root = root.to_path_buf();
+    let binary = tokio::task::spawn_blocking(move || -> Result<HashSet<String>> {
+      let repo = gix::open(&root).map_err(|_| AppError::NotGitRepository { dir: root })?;
+
+      let index = repo
+        .index()
+        .map_err(|e| AppError::GitError(format!("{}", e)))?;
+
+      let mut binary = HashSet::new();
+
+      for entry in index.entries() {
+        let path = entry.path(&index).to_string();
+
+        if !files.contains(&path) {
+          continue;
+        }
+
+        // Содержимое берем из индекса: проверяется то, что будет закоммичено
+        let Ok(object) = repo.find_object(entry.id) else {
+          continue;
+        };
+
+        let head = &object.data[..object.data.len().min(BINARY_CHECK_BYTES)];
+
+        if head.contains(&0) {
+          binary.insert(path);
+        }
+      }
+
+      Ok(binary)
+    })
+    .await??;
+
+    Ok(
+      file_commands
+        .into_iter()
+        .filter(|file_cmd| {
+          file_cmd.binary || !file_cmd.files.is_empty() || !binary.contains(&file_cmd.filename)
+        })
+        .collect(),
+    )
+  }
+
   pub fn match_files_to_commands(
     config: &Config,
     changed_files: &[String],
-  ) -> Result<Vec<FileCommand>> {
-    let groups = config.parse_groups();
+    mode: Mode,
+  ) -> Result<MatchedCommands> {
+    let patterns = FilePatterns::new(config, mode)?;
+
+    Self::match_prematched(config, &patterns, changed_files, &HashMap::new(), mode)
+  }
+
+  /// Matches files like [`FileCommand::match_files_to_commands`] with patterns parsed
+  /// up front; files in `prematched` were already matched while the index was read.
+  pub fn match_prematched(
+    config: &Config,
+    patterns: &FilePatterns,
+    changed_files: &[String],
+    prematched: &HashMap<String, FileMatch>,
+    mode: Mode,
+  ) -> Result<MatchedCommands> {
     let mut file_commands = Vec::new();
-    let mut all_patterns: Vec<String> = Vec::new();
+    let mut uncovered_files: Vec<String> = Vec::new();
+    let mut seen: HashMap<(String, String), bool> = HashMap::new();
+    // Команды групп со `scope = "group"`: (группа, команда, совпавшие файлы)
+    let mut group_scoped: Vec<(&Group, &CommandSpec, Vec<String>)> = Vec::new();
 
-    for group in &groups {
-      all_patterns.extend(group.patterns.keys().cloned());
-    }
+    // Плоский список (группа, паттерн, команды) в порядке проверки
+    let entries: Vec<(&Group, &String, &Vec<CommandSpec>)> = patterns
+      .entries
+      .iter()
+      .map(|(idx, pattern)| {
+        let group = &patterns.groups[*idx];
+        (group, pattern, &group.patterns[pattern])
+      })
+      .collect();
+    let all_patterns: Vec<String> = entries
+      .iter()
+      .map(|(_, pattern, _)| (*pattern).clone())
+      .collect();
+    // Сколько файлов пришло в каждый паттерн, для `--unused-patterns`
+    let mut hits = vec![0usize; entries.len()];
+    let mut has_candidates = false;
+    let overrides = config.parse_overrides(mode)?;
+    let overrides_group = config.overrides_group();
+    let no_files = Arc::new(Vec::new());
 
     for file in changed_files {
-      let mut matched = false;
-
-      for group in &groups {
-        for (pattern, commands) in &group.patterns {
-          if glob_match(pattern, file) {
-            for command in commands {
-              file_commands.push(FileCommand {
-                filename: file.clone(),
-                command: command.clone(),
-                group_name: group.name.clone(),
-                timeout: group.timeout.clone(),
-                execution_order: group.execution_order,
+      let file_match = prematched
+        .get(file)
+        .copied()
+        .unwrap_or_else(|| patterns.classify(file));
+
+      let matched: Option<(&Group, Vec<&CommandSpec>)> = match file_match {
+        FileMatch::Ignored => continue,
+        FileMatch::Pattern(idx) => {
+          hits[idx] += 1;
+          let (group, _, commands) = entries[idx];
+          Some((group, commands.iter().collect()))
+        }
+        FileMatch::Unmatched => None,
+      };
+
+      has_candidates = true;
+
+      let file_override = overrides.get(file);
+
+      if matched.is_none() && file_override.is_none() {
+        uncovered_files.push(file.clone());
+        continue;
+      }
+
+      // Переопределение без `append` заменяет команды группы
+      if let Some((group, commands)) = &matched
+        && file_override.is_none_or(|file_override| file_override.append)
+      {
+        if group.scope == Scope::Group {
+          for spec in commands {
+            let command = spec.to_command_line();
+            // Команда на всю группу запускается в ней один раз; у другой группы
+            // с той же командой своя задача со своими настройками
+            let existing = group_scoped
+              .iter_mut()
+              .find(|(scoped_group, scoped_spec, _)| {
+                scoped_group.name == group.name && scoped_spec.to_command_line() == command
               });
-            }
 
-            matched = true;
-            break;
+            match existing {
+              Some((_, _, files)) => files.push(file.clone()),
+              None => group_scoped.push((*group, *spec, vec![file.clone()])),
+            }
+          }
+        } else {
+          for spec in commands {
+            // Одинаковые команды для одного файла запускаем только один раз
+            file_commands.extend(FileCommand::from_steps(
+              group,
+              spec,
+              file.clone(),
+              no_files.clone(),
+              &mut seen,
+            )?);
           }
         }
+      }
+
+      // Команды переопределения всегда на файл, с настройками совпавшей группы
+      if let Some(file_override) = file_override {
+        let group = matched
+          .as_ref()
+          .map_or(&overrides_group, |(group, _)| *group);
 
-        if matched {
-          break;
+        for spec in &file_override.commands {
+          file_commands.extend(
+            FileCommand::from_steps(group, spec, file.clone(), no_files.clone(), &mut seen)?
+              .into_iter()
+              .map(|file_cmd| FileCommand {
+                from_override: true,
+                ..file_cmd
+              }),
+          );
         }
       }
     }
 
-    if file_commands.is_empty() && !changed_files.is_empty() {
+    let mut shared: Vec<Arc<Vec<String>>> = Vec::new();
+
+    for (group, spec, files) in group_scoped {
+      let label = format!("{} file(s)", files.len());
+      // Команды с одинаковым набором файлов получают один и тот же список
+      let files = match shared.iter().find(|list| ***list == files) {
+        Some(list) => list.clone(),
+        None => {
+          let list = Arc::new(files);
+          shared.push(list.clone());
+          list
+        }
+      };
+
+      // Пара (группа, команда) здесь уникальна, а подпись "N file(s)" может совпасть
+      // у разных групп, поэтому повторы ищутся только внутри команды
+      file_commands.extend(FileCommand::from_steps(
+        group,
+        spec,
+        label,
+        files,
+        &mut HashMap::new(),
+      )?);
+    }
+
+    if config.strict_coverage && !uncovered_files.is_empty() {
+      return Err(AppError::UncoveredFiles {
+        files: uncovered_files,
+      });
+    }
+
+    if file_commands.is_empty() && has_candidates {
       return Err(AppError::NoFilesMatched {
         patterns: all_patterns,
       });
     }
 
-    Ok(file_commands)
+    let unmatched_patterns = entries
+      .iter()
+      .zip(hits)
+      .filter(|(_, hits)| *hits == 0)
+      .map(|((group, pattern, _), _)| (group.name.clone(), (*pattern).clone()))
+      .collect();
+
+    Ok(MatchedCommands {
+      file_commands,
+      unmatched_patterns,
+    })
+  }
+}
+
+/// Tree of the `HEAD` commit, or `None` before the first commit.
+///
+/// With an unborn `HEAD` every staged file is an added one, so callers compare
+/// against an empty tree; other failures to read `HEAD` are reported.
+pub fn head_tree_id(repo: &gix::Repository) -> Result<Option<gix::ObjectId>> {
+  let head = repo
+    .head()
+    .map_err(|e| AppError::GitError(format!("{}", e)))?;
+
+  if head.is_unborn() {
+    return Ok(None);
+  }
+
+  repo
+    .head_tree_id()
+    .map(|id| Some(id.detach()))
+    .map_err(|e| AppError::GitError(format!("cannot read the tree of HEAD: {}", e)))
+}
+
+/// Patterns of all groups in checking order, parsed once and shared with the
+/// thread that matches files while the index is read.
+#[derive(Debug)]
+pub struct FilePatterns {
+  groups: Vec<Group>,
+  // (индекс группы в `groups`, паттерн)
+  entries: Vec<(usize, String)>,
+  matcher: PatternMatcher,
+  ignore: PatternMatcher,
+}
+
+/// Where a file goes before its commands are resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileMatch {
+  /// Listed in `.fast-stagedignore`.
+  Ignored,
+  /// Index of the first matching pattern in [`FilePatterns`].
+  Pattern(usize),
+  Unmatched,
+}
+
+impl FilePatterns {
+  pub fn new(config: &Config, mode: Mode) -> Result<Self> {
+    let groups = config.parse_groups(mode)?;
+
+    // Паттерны группы "default" идут в конце: до них доходят только файлы без других совпадений
+    let mut ordered: Vec<(usize, &Group)> = groups.iter().enumerate().collect();
+    ordered.sort_by_key(|(_, group)| group.name == DEFAULT_GROUP);
+
+    let entries: Vec<(usize, String)> = ordered
+      .into_iter()
+      .flat_map(|(idx, group)| {
+        group
+          .patterns
+          .keys()
+          .map(move |pattern| (idx, pattern.clone()))
+      })
+      .collect();
+
+    // Паттерны разбираются один раз, а не для каждой пары файл/паттерн
+    let matcher = PatternMatcher::new(entries.iter().map(|(_, pattern)| pattern.as_str()));
+
+    Ok(Self {
+      groups,
+      entries,
+      matcher,
+      ignore: PatternMatcher::new(&config.ignore),
+    })
+  }
+
+  pub fn classify(&self, file: &str) -> FileMatch {
+    // Файлы из `.fast-stagedignore` исключаются до проверки паттернов групп
+    if self.ignore.first_match(file).is_some() {
+      return FileMatch::Ignored;
+    }
+
+    match self.matcher.first_match(file) {
+      Some(idx) => FileMatch::Pattern(idx),
+      None => FileMatch::Unmatched,
+    }
+  }
+}
+
+/// Commands for the staged files and the patterns no file reached.
+#[derive(Debug, Default)]
+pub struct MatchedCommands {
+  pub file_commands: Vec<FileCommand>,
+  // (группа, паттерн) без единого совпадения в этом коммите
+  pub unmatched_patterns: Vec<(String, String)>,
+}
+
+/// Looks `program` up in PATH; a relative path is resolved against `root`.
+fn program_exists(program: &str, root: &Path) -> bool {
+  which::which_in(program, std::env::var_os("PATH"), root).is_ok()
+}
+
+/// Named groups of the group's `capture` regex matched against `file`.
+///
+/// A path the regex does not match gets no substitutions; a group that did not
+/// take part in the match is substituted with an empty string.
+fn path_captures(group: &Group, file: &str) -> Vec<(String, String)> {
+  let Some(regex) = &group.capture else {
+    return Vec::new();
+  };
+  let Some(captures) = regex.captures(file) else {
+    return Vec::new();
+  };
+
+  regex
+    .capture_names()
+    .flatten()
+    // Встроенные подстановки не переопределяются
+    .filter(|name| !matches!(*name, "file" | "files" | "ranges"))
+    .map(|name| {
+      let value = captures.name(name).map_or("", |value| value.as_str());
+      (name.to_string(), value.to_string())
+    })
+    .collect()
+}
+
+/// Quotes an argument so the shell passes it through unchanged.
+pub fn shell_quote(arg: &str) -> String {
+  let is_safe = !arg.is_empty()
+    && arg
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || "-_=./:,+@%".contains(c));
+
+  if is_safe {
+    arg.to_string()
+  } else {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn commands(config: &str, files: &[&str]) -> Vec<(String, String, String)> {
+    let config = Config::from_content(Path::new(".fast-staged.toml"), config, None).unwrap();
+    let files: Vec<String> = files.iter().map(|file| file.to_string()).collect();
+
+    let mut commands: Vec<(String, String, String)> =
+      FileCommand::match_files_to_commands(&config, &files, Mode::Fix)
+        .unwrap()
+        .file_commands
+        .into_iter()
+        .map(|file_cmd| (file_cmd.filename, file_cmd.group_name, file_cmd.command))
+        .collect();
+    commands.sort();
+
+    commands
+  }
+
+  #[test]
+  fn default_group_patterns_apply_only_to_files_no_other_group_matched() {
+    let commands = commands(
+      r#"
+      [lint.patterns]
+      "*.js" = ["eslint"]
+
+      [default.patterns]
+      "*.js" = ["prettier --check"]
+      "*.md" = ["markdownlint"]
+      "#,
+      &["a.js", "b.md", "c.png"],
+    );
+
+    let expected = [
+      ("a.js", "lint", "eslint"),
+      ("b.md", "default", "markdownlint"),
+    ];
+
+    assert_eq!(
+      commands,
+      expected.map(|(file, group, command)| (file.into(), group.into(), command.into()))
+    );
+  }
+
+  #[test]
+  fn head_tree_id_is_none_before_the_first_commit() {
+    let dir = std::env::temp_dir().join(format!("fast-staged-unborn-{}", std::process::id()));
+    let repo = gix::init(&dir).unwrap();
+
+    let tree = head_tree_id(&repo);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(tree.unwrap(), None);
+  }
+
+  #[test]
+  fn plain_duplicates_run_once_and_chains_keep_every_step() {
+    let commands = commands(
+      r#"
+      [lint.patterns]
+      "*.js" = ["eslint", "eslint", { chain = ["prettier --write", "tsc"] }]
+      "#,
+      &["a.js"],
+    );
+
+    let expected = ["eslint", "prettier --write", "tsc"];
+
+    assert_eq!(
+      commands,
+      expected.map(|command| ("a.js".into(), "lint".into(), command.into()))
+    );
+  }
+
+  #[test]
+  fn rejects_chain_steps_that_repeat_a_command() {
+    let configs = [
+      r#"
+      [fmt.patterns]
+      "*.js" = [{ chain = ["prettier --write", "eslint --fix", "prettier --write"] }]
+      "#,
+      r#"
+      [lint.patterns]
+      "*.js" = ["eslint", { chain = ["eslint", "prettier"] }]
+      "#,
+    ];
+
+    for config in configs {
+      let config = Config::from_content(Path::new(".fast-staged.toml"), config, None).unwrap();
+      let result = FileCommand::match_files_to_commands(&config, &["a.js".to_string()], Mode::Fix);
+
+      assert!(matches!(
+        result,
+        Err(AppError::DuplicateChainStep { file, .. }) if file == "a.js"
+      ));
+    }
+  }
+
+  #[test]
+  fn group_scope_commands_stay_separate_per_group() {
+    let config = r#"
+      [web]
+      scope = "group"
+      env = { TARGET = "web" }
+      patterns = { "*.ts" = ["tsc"] }
+
+      [server]
+      scope = "group"
+      env = { TARGET = "server" }
+      patterns = { "*.mts" = ["tsc"] }
+      "#;
+    let config = Config::from_content(Path::new(".fast-staged.toml"), config, None).unwrap();
+    let files: Vec<String> = ["a.ts", "b.ts", "c.mts"].map(String::from).to_vec();
+
+    let mut commands: Vec<(String, Vec<String>, String)> =
+      FileCommand::match_files_to_commands(&config, &files, Mode::Fix)
+        .unwrap()
+        .file_commands
+        .into_iter()
+        .map(|file_cmd| {
+          (
+            file_cmd.group_name,
+            file_cmd.files.to_vec(),
+            file_cmd.env["TARGET"].clone(),
+          )
+        })
+        .collect();
+    commands.sort();
+
+    assert_eq!(
+      commands,
+      vec![
+        ("server".into(), vec!["c.mts".into()], "server".into()),
+        (
+          "web".into(),
+          vec!["a.ts".into(), "b.ts".into()],
+          "web".into()
+        ),
+      ]
+    );
   }
 }
@@ -0,0 +1,134 @@
+use crate::app::{AppError, Result};
+use crate::config::{Config, Mode};
+use std::path::{Path, PathBuf};
+
+/// File written by `fast-staged init`.
+pub const INIT_FILE: &str = ".fast-staged.toml";
+
+const HEADER: &str = r#"# fast-staged config: every table is a group of glob patterns and the commands
+# to run for the staged files they match. {file} is replaced with the file path.
+# Print the JSON Schema with `fast-staged schema` for editor completion.
+
+# Timeout for every command
+timeout = "60s"
+
+# Status symbols for terminals without unicode
+# symbols = "ascii"
+"#;
+
+const JS: &str = r#"
+# JavaScript and TypeScript
+[js]
+# Groups with the same mutex never run at the same time
+mutex = "node"
+
+[js.patterns]
+"*.{js,jsx,ts,tsx,mjs,cjs}" = { fix = ["npx eslint --fix {file}"], check = ["npx eslint {file}"] }
+"*.{json,css,md,yml,yaml}" = ["npx prettier --write {file}"]
+"#;
+
+const RUST: &str = r#"
+# Rust
+[rust]
+# Run the commands of a file one after another
+execution_order = "sequential"
+
+[rust.patterns]
+"**/*.rs" = { fix = ["rustfmt --edition 2024 {file}"], check = ["rustfmt --edition 2024 --check {file}"] }
+"#;
+
+const PYTHON: &str = r#"
+# Python
+[python.patterns]
+"**/*.py" = { fix = ["ruff format {file}", "ruff check --fix {file}"], check = ["ruff format --check {file}", "ruff check {file}"] }
+"#;
+
+/// Project types the starter config has example groups for.
+struct Preset {
+  markers: &'static [&'static str],
+  template: &'static str,
+}
+
+const PRESETS: &[Preset] = &[
+  Preset {
+    markers: &["package.json"],
+    template: JS,
+  },
+  Preset {
+    markers: &["Cargo.toml"],
+    template: RUST,
+  },
+  Preset {
+    markers: &["pyproject.toml", "setup.py", "requirements.txt"],
+    template: PYTHON,
+  },
+];
+
+/// Writes a starter config to `dir` and returns its path.
+///
+/// Groups for the project types found in `dir` are enabled, the others stay
+/// commented out as examples. An existing config is kept unless `force` is set.
+pub fn init(dir: &Path, force: bool) -> Result<PathBuf> {
+  let path = dir.join(INIT_FILE);
+
+  if !force && let Some(existing) = existing_config(dir) {
+    return Err(AppError::ConfigExists { path: existing });
+  }
+
+  let content = starter_config(dir);
+
+  // Шаблон должен читаться теми же структурами, что и обычный конфиг
+  let config = Config::from_content(&path, &content, Some("toml"))?;
+  config.parse_groups(Mode::Fix)?;
+  config.parse_groups(Mode::Check)?;
+
+  std::fs::write(&path, content)?;
+
+  Ok(path)
+}
+
+fn starter_config(dir: &Path) -> String {
+  let mut content = HEADER.to_string();
+
+  for preset in PRESETS {
+    let detected = preset
+      .markers
+      .iter()
+      .any(|marker| dir.join(marker).is_file());
+
+    if detected {
+      content.push_str(preset.template);
+    } else {
+      content.push_str(&comment_out(preset.template));
+    }
+  }
+
+  content
+}
+
+/// Comments out a template, keeping its own comments as they are.
+fn comment_out(template: &str) -> String {
+  template
+    .lines()
+    .map(|line| {
+      if line.is_empty() || line.starts_with('#') {
+        format!("{}\n", line)
+      } else {
+        format!("# {}\n", line)
+      }
+    })
+    .collect()
+}
+
+/// Dedicated config files that `init` must not shadow or overwrite.
+fn existing_config(dir: &Path) -> Option<PathBuf> {
+  [
+    ".fast-staged.toml",
+    "fast-staged.toml",
+    ".fast-staged.json",
+    "fast-staged.json",
+  ]
+  .into_iter()
+  .map(|name| dir.join(name))
+  .find(|path| path.is_file())
+}
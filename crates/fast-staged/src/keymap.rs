@@ -0,0 +1,131 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// What a key does in the TUI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+  SelectPrevious,
+  SelectNext,
+  ToggleGroup,
+  ToggleGroupBy,
+  Quit,
+}
+
+/// When a binding is active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyContext {
+  Always,
+  /// While tasks run, and on a final screen that closes by itself.
+  Running,
+  /// On the final screen waiting for a key (`wait_on_exit = "manual"`).
+  Finished,
+}
+
+impl KeyContext {
+  fn is_active(self, awaiting_dismiss: bool) -> bool {
+    match self {
+      KeyContext::Always => true,
+      KeyContext::Running => !awaiting_dismiss,
+      KeyContext::Finished => awaiting_dismiss,
+    }
+  }
+}
+
+/// A key binding: both the handler and the hint in the status bar read it.
+#[derive(Debug, Clone, Copy)]
+pub struct KeyBinding {
+  /// Empty matches any key that no earlier binding took.
+  pub codes: &'static [KeyCode],
+  pub ctrl: bool,
+  pub keys: &'static str,
+  pub description: &'static str,
+  pub action: Action,
+  pub context: KeyContext,
+}
+
+/// All bindings, checked in order.
+pub const KEYMAP: &[KeyBinding] = &[
+  KeyBinding {
+    codes: &[KeyCode::Up, KeyCode::Char('k')],
+    ctrl: false,
+    keys: "↑/k",
+    description: "up",
+    action: Action::SelectPrevious,
+    context: KeyContext::Always,
+  },
+  KeyBinding {
+    codes: &[KeyCode::Down, KeyCode::Char('j')],
+    ctrl: false,
+    keys: "↓/j",
+    description: "down",
+    action: Action::SelectNext,
+    context: KeyContext::Always,
+  },
+  KeyBinding {
+    codes: &[KeyCode::Enter, KeyCode::Char(' ')],
+    ctrl: false,
+    keys: "Enter",
+    description: "collapse",
+    action: Action::ToggleGroup,
+    context: KeyContext::Always,
+  },
+  KeyBinding {
+    codes: &[KeyCode::Char('g')],
+    ctrl: false,
+    keys: "g",
+    description: "group by file/group",
+    action: Action::ToggleGroupBy,
+    context: KeyContext::Always,
+  },
+  KeyBinding {
+    codes: &[KeyCode::Esc, KeyCode::Char('q')],
+    ctrl: false,
+    keys: "q/Esc",
+    description: "stop",
+    action: Action::Quit,
+    context: KeyContext::Running,
+  },
+  KeyBinding {
+    codes: &[KeyCode::Char('c'), KeyCode::Char('C')],
+    ctrl: true,
+    keys: "Ctrl-C",
+    description: "stop",
+    action: Action::Quit,
+    context: KeyContext::Running,
+  },
+  KeyBinding {
+    codes: &[],
+    ctrl: false,
+    keys: "any key",
+    description: "exit",
+    action: Action::Quit,
+    context: KeyContext::Finished,
+  },
+];
+
+impl KeyBinding {
+  fn matches(&self, key: &KeyEvent) -> bool {
+    if self.codes.is_empty() {
+      return true;
+    }
+
+    let ctrl = key.modifiers == KeyModifiers::CONTROL;
+
+    self.ctrl == ctrl && self.codes.contains(&key.code)
+  }
+}
+
+/// The action of the first active binding that matches `key`.
+pub fn action_for(key: &KeyEvent, awaiting_dismiss: bool) -> Option<Action> {
+  KEYMAP
+    .iter()
+    .filter(|binding| binding.context.is_active(awaiting_dismiss))
+    .find(|binding| binding.matches(key))
+    .map(|binding| binding.action)
+}
+
+/// Bindings to show in the status bar right now.
+pub fn active_bindings(awaiting_dismiss: bool) -> impl Iterator<Item = &'static KeyBinding> {
+  KEYMAP
+    .iter()
+    .filter(move |binding| binding.context.is_active(awaiting_dismiss))
+}
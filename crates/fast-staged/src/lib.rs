@@ -1,14 +1,147 @@
 mod app;
+mod cache;
+mod cli;
 mod command;
 mod config;
+mod diff;
 mod event;
+mod fanout;
 mod file;
+mod init;
+mod keymap;
+mod lock;
+pub mod matcher;
 mod model;
+mod output;
+mod profile;
+mod provider;
+mod pty;
 mod render;
+pub mod report;
+mod schema;
+mod snapshot;
 mod task;
+mod usage;
+mod validate;
 
 use crate::app::App;
+use crate::cli::{Cli, Command};
+use crate::config::Config;
+use crate::file::FileCommand;
+use crate::provider::{InlineConfig, StaticFiles};
+use clap::Parser;
+
+/// Version of the native crate.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Output modes and reports the binary can produce: the TUI, the prefixed line log
+/// without a terminal, `--json`, `--ndjson`, GitHub Actions annotations and `--html-report`.
+pub const REPORTERS: &[&str] = &["tui", "line", "json", "ndjson", "github", "html"];
+
+/// Config sources checked during discovery, in priority order: the
+/// `FAST_STAGED_CONFIG` variable, then the config files.
+pub fn config_formats() -> Vec<&'static str> {
+  std::iter::once(config::CONFIG_ENV)
+    .chain(config::CONFIG_FILES.iter().map(|(file, _)| *file))
+    .collect()
+}
 
 pub async fn run() -> color_eyre::Result<()> {
-  App::new().run().await
+  let cli = Cli::parse();
+
+  match cli.command {
+    Some(Command::Schema) => {
+      println!(
+        "{}",
+        serde_json::to_string_pretty(&schema::config_schema())?
+      );
+      Ok(())
+    }
+    Some(Command::Init { force }) => {
+      let path = init::init(&std::env::current_dir()?, force)?;
+      println!("Created {}", path.display());
+      Ok(())
+    }
+    None if cli.list_groups => {
+      let start_dir = match cli.repo.first() {
+        Some(path) => path.clone(),
+        None => std::env::current_dir()?,
+      };
+
+      // Конфиг ищется от корня репозитория, как при обычном запуске
+      let root = FileCommand::repo_root(&start_dir).unwrap_or(start_dir);
+
+      let mut groups = Config::load(&root)?.parse_groups(cli.mode)?;
+      groups.sort_by(|a, b| a.name.cmp(&b.name));
+      print!("{}", render::groups_table(&groups));
+      Ok(())
+    }
+    // Репозитории запускаются параллельно, отчеты собираются в один
+    None if cli.repo.len() > 1 => fanout::run_repos(&cli).await,
+    None => App::new(cli).run().await,
+  }
+}
+
+/// Runs fast-staged with the TUI on the current terminal and returns the result
+/// of every task together with their totals.
+///
+/// `args` are command line arguments without the program name. The summary is
+/// printed as in the CLI, but the process is never exited: errors are returned.
+pub async fn run_interactive<I, S>(args: I) -> color_eyre::Result<report::RunReport>
+where
+  I: IntoIterator<Item = S>,
+  S: Into<std::ffi::OsString> + Clone,
+{
+  let cli = Cli::try_parse_from(
+    std::iter::once(std::ffi::OsString::from("fast-staged"))
+      .chain(args.into_iter().map(Into::into)),
+  )?;
+
+  App::new(cli).run_reporting().await
+}
+
+/// Runs fast-staged without the TUI and returns the result of every task
+/// together with their totals.
+///
+/// `args` are command line arguments without the program name. Nothing is
+/// printed and the process is never exited, so this is safe to embed.
+pub async fn run_headless<I, S>(args: I) -> color_eyre::Result<report::RunReport>
+where
+  I: IntoIterator<Item = S>,
+  S: Into<std::ffi::OsString> + Clone,
+{
+  let cli = Cli::try_parse_from(
+    std::iter::once(std::ffi::OsString::from("fast-staged"))
+      .chain(args.into_iter().map(Into::into)),
+  )?;
+
+  App::headless(cli).run_headless().await
+}
+
+/// Like [`run_headless`], but with a fixed list of files and config text instead
+/// of the git index and the config file.
+///
+/// No repository is needed, paths are relative to the current directory. The
+/// config is JSON when it starts with `{` and TOML otherwise.
+pub async fn run_headless_with<I, S>(
+  args: I,
+  files: Vec<String>,
+  config: &str,
+) -> color_eyre::Result<report::RunReport>
+where
+  I: IntoIterator<Item = S>,
+  S: Into<std::ffi::OsString> + Clone,
+{
+  let cli = Cli::try_parse_from(
+    std::iter::once(std::ffi::OsString::from("fast-staged"))
+      .chain(args.into_iter().map(Into::into)),
+  )?;
+
+  App::headless(cli)
+    .with_providers(
+      Box::new(StaticFiles(files)),
+      Box::new(InlineConfig(config.to_string())),
+    )
+    .run_headless()
+    .await
 }
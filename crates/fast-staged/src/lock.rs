@@ -0,0 +1,63 @@
+use crate::app::{AppError, Result};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::path::Path;
+
+/// Directory with the lock, cache and resume state of fast-staged itself.
+pub const STATE_DIR: &str = ".fast-staged";
+
+/// Lock file that keeps two runs from touching the index at the same time.
+pub const LOCK_PATH: &str = ".fast-staged/lock";
+
+/// Creates the state directory in the repository at `root`.
+///
+/// A `.gitignore` with `*` inside keeps it out of `git status` and `git add .`.
+pub fn create_state_dir(root: &Path) -> Result<()> {
+  let dir = root.join(STATE_DIR);
+  let gitignore = dir.join(".gitignore");
+
+  fs::create_dir_all(&dir)?;
+
+  if !gitignore.exists() {
+    fs::write(gitignore, "*\n")?;
+  }
+
+  Ok(())
+}
+
+/// Advisory lock held for the whole run, released when dropped.
+///
+/// The OS drops the lock together with the process, so a crashed run never
+/// leaves the repository locked.
+pub struct RunLock {
+  file: File,
+}
+
+impl RunLock {
+  /// Takes the lock in the repository at `root`, failing right away if another instance holds it.
+  pub fn acquire(root: &Path) -> Result<Self> {
+    let path = root.join(LOCK_PATH);
+
+    create_state_dir(root)?;
+
+    let file = OpenOptions::new()
+      .create(true)
+      .truncate(false)
+      .write(true)
+      .open(&path)?;
+
+    match file.try_lock() {
+      Ok(()) => Ok(Self { file }),
+      Err(TryLockError::WouldBlock) => Err(AppError::AlreadyRunning {
+        path: LOCK_PATH.to_string(),
+      }),
+      Err(TryLockError::Error(err)) => Err(err.into()),
+    }
+  }
+}
+
+impl Drop for RunLock {
+  fn drop(&mut self) {
+    // Файл остается на месте: удаление открыло бы гонку с тем, кто его уже открыл
+    let _ = self.file.unlock();
+  }
+}
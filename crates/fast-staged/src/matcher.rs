@@ -0,0 +1,243 @@
+use fast_glob::glob_match;
+
+/// Glob metacharacters understood by `fast_glob`.
+const GLOB_SPECIAL: &[char] = &['*', '?', '[', ']', '{', '}', '(', ')', '!', '\\'];
+
+/// A glob pattern prepared once for matching many files.
+#[derive(Debug, Clone)]
+struct CompiledPattern {
+  pattern: String,
+  /// Literal tail every matching path must end with (e.g. `.rs` for `**/*.rs`).
+  suffix: Option<String>,
+  /// Pattern without a slash, matched against the file name only (like gitignore).
+  basename: bool,
+}
+
+impl CompiledPattern {
+  fn new(pattern: String) -> Self {
+    let suffix = literal_suffix(&pattern);
+    let basename = !pattern.contains('/');
+
+    Self {
+      pattern,
+      suffix,
+      basename,
+    }
+  }
+
+  fn is_match(&self, file: &str) -> bool {
+    // Дешевая проверка суффикса отсекает большинство файлов до полного сопоставления
+    if let Some(suffix) = &self.suffix
+      && !file.ends_with(suffix.as_str())
+    {
+      return false;
+    }
+
+    // `*.js` должен находить и `src/deep/app.js`, как в lint-staged
+    if self.basename {
+      let name = file.rsplit('/').next().unwrap_or(file);
+      return glob_match(&self.pattern, name);
+    }
+
+    glob_match(&self.pattern, file)
+  }
+}
+
+/// Matches files against an ordered list of glob patterns.
+///
+/// Patterns without a `/` match the file name in any directory, patterns
+/// with a `/` match the path relative to the repository root.
+///
+/// Patterns are analyzed once up front so that matching a large set of files
+/// skips the full glob match for patterns that can't possibly apply.
+#[derive(Debug, Clone, Default)]
+pub struct PatternMatcher {
+  patterns: Vec<CompiledPattern>,
+}
+
+impl PatternMatcher {
+  pub fn new<I, S>(patterns: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    Self {
+      patterns: patterns
+        .into_iter()
+        .map(|pattern| CompiledPattern::new(pattern.into()))
+        .collect(),
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.patterns.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.patterns.is_empty()
+  }
+
+  /// Returns the index of the first pattern that matches `file`.
+  pub fn first_match(&self, file: &str) -> Option<usize> {
+    self
+      .patterns
+      .iter()
+      .position(|pattern| pattern.is_match(file))
+  }
+}
+
+/// Expands `{a,b}` alternatives into separate patterns, nested braces included.
+///
+/// `src/*.{js,ts}` becomes `src/*.js` and `src/*.ts`, so each alternative gets
+/// its own suffix and basename analysis. Braces without a top-level comma,
+/// escaped or unbalanced braces are kept as they are, and so are negated
+/// patterns: `!*.{js,ts}` is not the same as two separate negations.
+pub fn expand_braces(pattern: &str) -> Vec<String> {
+  if pattern.starts_with('!') {
+    return vec![pattern.to_string()];
+  }
+
+  let chars: Vec<char> = pattern.chars().collect();
+  let mut start = 0;
+
+  while let Some(open) =
+    (start..chars.len()).find(|&idx| chars[idx] == '{' && !is_escaped(&chars, idx))
+  {
+    let mut depth = 0;
+    let mut commas = Vec::new();
+    let mut close = None;
+
+    for idx in open..chars.len() {
+      if is_escaped(&chars, idx) {
+        continue;
+      }
+
+      match chars[idx] {
+        '{' => depth += 1,
+        '}' => {
+          depth -= 1;
+          if depth == 0 {
+            close = Some(idx);
+            break;
+          }
+        }
+        ',' if depth == 1 => commas.push(idx),
+        _ => {}
+      }
+    }
+
+    let Some(close) = close else {
+      break;
+    };
+
+    // `{a}` без запятой - не перечисление, ищем следующую скобку
+    if commas.is_empty() {
+      start = open + 1;
+      continue;
+    }
+
+    let prefix: String = chars[..open].iter().collect();
+    let suffix: String = chars[close + 1..].iter().collect();
+    let mut separators = vec![open];
+    separators.extend(commas);
+    separators.push(close);
+
+    // Каждая альтернатива раскрывается заново: в ней и в хвосте могут быть свои скобки
+    return separators
+      .windows(2)
+      .flat_map(|bounds| {
+        let alternative: String = chars[bounds[0] + 1..bounds[1]].iter().collect();
+        expand_braces(&format!("{}{}{}", prefix, alternative, suffix))
+      })
+      .collect();
+  }
+
+  vec![pattern.to_string()]
+}
+
+/// Turns a `.fast-stagedignore` entry into globs that also cover what is inside a directory.
+///
+/// As in `.gitignore`, an entry without a slash (`node_modules`) applies at any depth,
+/// a leading slash anchors it to the repository root and a trailing slash limits it
+/// to directories. Negated entries are kept as they are.
+pub fn ignore_globs(entry: &str) -> Vec<String> {
+  if entry.starts_with('!') {
+    return vec![entry.to_string()];
+  }
+
+  let (entry, dir_only) = match entry.strip_suffix('/') {
+    Some(entry) => (entry, true),
+    None => (entry, false),
+  };
+  let path = match entry.strip_prefix('/') {
+    Some(entry) => entry.to_string(),
+    None if entry.contains('/') => entry.to_string(),
+    None => format!("**/{}", entry),
+  };
+
+  let contents = format!("{}/**", path);
+
+  if dir_only {
+    vec![contents]
+  } else {
+    vec![path, contents]
+  }
+}
+
+fn is_escaped(chars: &[char], idx: usize) -> bool {
+  idx > 0 && chars[idx - 1] == '\\'
+}
+
+/// Extracts the literal text after the last glob metacharacter.
+///
+/// Negated patterns and patterns ending in a metacharacter have no usable suffix.
+fn literal_suffix(pattern: &str) -> Option<String> {
+  if pattern.starts_with('!') {
+    return None;
+  }
+
+  let tail = match pattern.rfind(GLOB_SPECIAL) {
+    Some(idx) => &pattern[idx + 1..],
+    None => pattern,
+  };
+
+  (!tail.is_empty()).then(|| tail.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn ignore(entries: &[&str]) -> PatternMatcher {
+    PatternMatcher::new(entries.iter().flat_map(|entry| ignore_globs(entry)))
+  }
+
+  #[test]
+  fn ignore_entry_without_slash_covers_directory_contents() {
+    let matcher = ignore(&["node_modules", "dist"]);
+
+    assert!(matcher.first_match("node_modules/pkg/index.js").is_some());
+    assert!(matcher.first_match("packages/app/dist/bundle.js").is_some());
+    assert!(matcher.first_match("src/dist").is_some());
+    assert!(matcher.first_match("src/distance.js").is_none());
+  }
+
+  #[test]
+  fn ignore_entry_with_slash_is_anchored_to_the_root() {
+    let matcher = ignore(&["/build", "docs/generated/"]);
+
+    assert!(matcher.first_match("build/out.js").is_some());
+    assert!(matcher.first_match("src/build/out.js").is_none());
+    assert!(matcher.first_match("docs/generated/api.md").is_some());
+    assert!(matcher.first_match("docs/generated").is_none());
+  }
+
+  #[test]
+  fn ignore_glob_entry_matches_at_any_depth() {
+    let matcher = ignore(&["*.log"]);
+
+    assert!(matcher.first_match("debug.log").is_some());
+    assert!(matcher.first_match("logs/deep/debug.log").is_some());
+    assert!(matcher.first_match("debug.log.js").is_none());
+  }
+}
@@ -1,15 +1,192 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+use crate::command::{CommandStatus, Symbols};
+use crate::config::{GroupBy, SortOrder, StatsSort, TaskSort};
+use crate::usage::ResourceUsage;
 use ratatui::style::Color;
 
+/// A rendered task: text, color, duration in ms and, for a running task with a
+/// timeout, the share of the timeout used so far.
+pub type TaskLine = (String, Color, u128, Option<f64>);
+
 #[derive(Debug, Default, Clone)]
 pub struct StateModel {
   pub running: bool,
+  // Идет поиск staged файлов, задачи еще не созданы
+  pub scanning: bool,
   pub total_execution_time: u128,
   pub elapsed_time: u128,
+  // Бюджет времени (`max_time`) в миллисекундах, для обратного отсчета
+  pub max_time: Option<u128>,
   pub command_stats: HashMap<String, (usize, u128)>,
-  pub command_lines: Vec<(String, Color, u128)>,
+  // Суммарное время CPU и пиковая память по командам
+  pub command_usage: HashMap<String, ResourceUsage>,
+  // Панель статистики скрыта (`--no-stats`), список задач занимает ее место
+  pub hide_stats: bool,
+  pub task_sort: TaskSort,
+  // Чем сгруппирован список задач: группами конфига или файлами
+  pub group_by: GroupBy,
+  pub stats_sort: StatsSort,
+  pub stats_order: SortOrder,
+  pub symbols: Symbols,
+  // Число различных файлов, попавших в каждую группу
+  pub group_file_counts: HashMap<String, usize>,
+  pub command_lines: Vec<TaskLine>,
+  // Самые долгие задачи, по убыванию длительности
+  pub slowest_tasks: Vec<(String, u128)>,
+  // Число различных файлов, для которых есть задачи
   pub total_files: usize,
+  // Число задач (уникальных пар файл + команда)
   pub statuses_count: usize,
+  // Сводка по статусам: "12 done, 2 failed, ..."
+  pub status_rollup: String,
+  // Число задач в очереди, в работе и завершенных
+  pub queue: QueueStats,
   pub is_empty: bool,
+  pub modified_files: Option<Vec<String>>,
+  pub awaiting_dismiss: bool,
+  // Задачи, сгруппированные по группам конфига, в порядке имен групп
+  pub groups: Vec<GroupView>,
+  // Индекс выбранного заголовка группы
+  pub selected_group: usize,
+  // Выбранная задача внутри группы, None - выбран заголовок
+  pub selected_task: Option<usize>,
+  // Заголовок и хвост вывода выбранной задачи для панели вывода
+  pub output_pane: Option<(String, String)>,
+  // Первая выполняющаяся задача, "команда on файл", для компактного режима
+  pub running_task: Option<String>,
+  // Свернутые группы, показывается только заголовок
+  pub collapsed_groups: HashSet<String>,
+}
+
+/// Task counts by execution stage, shown as the queue gauge.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct QueueStats {
+  pub waiting: usize,
+  pub running: usize,
+  pub finished: usize,
+}
+
+impl QueueStats {
+  pub fn from_statuses(statuses: &[CommandStatus]) -> Self {
+    let mut stats = Self::default();
+
+    for status in statuses {
+      match status {
+        CommandStatus::Waiting | CommandStatus::None => stats.waiting += 1,
+        CommandStatus::Running => stats.running += 1,
+        _ => stats.finished += 1,
+      }
+    }
+
+    stats
+  }
+
+  /// Share of unfinished tasks that are running right now.
+  ///
+  /// A low value while tasks are queued means they wait on locks, sequential
+  /// groups or earlier priorities rather than on the CPU.
+  pub fn utilization(&self) -> f64 {
+    let pending = self.waiting + self.running;
+
+    if pending == 0 {
+      0.0
+    } else {
+      self.running as f64 / pending as f64
+    }
+  }
+}
+
+/// A config group with the rendered lines of its tasks.
+#[derive(Debug, Default, Clone)]
+pub struct GroupView {
+  pub name: String,
+  // Сводка по статусам задач группы
+  pub rollup: String,
+  pub color: Color,
+  pub tasks: Vec<TaskLine>,
+  // Индексы задач в пуле, в порядке `tasks`
+  pub indices: Vec<usize>,
+}
+
+impl StateModel {
+  /// Moves the selection one visible row up: to the previous task or group header.
+  pub fn select_previous(&mut self) {
+    match self.selected_task {
+      Some(0) => self.selected_task = None,
+      Some(task) => self.selected_task = Some(task - 1),
+      None if self.selected_group > 0 => {
+        self.selected_group -= 1;
+        self.selected_task = self.visible_tasks(self.selected_group).checked_sub(1);
+      }
+      None => {}
+    }
+  }
+
+  /// Moves the selection one visible row down: into the group's tasks or to the next header.
+  pub fn select_next(&mut self) {
+    let next_task = self.selected_task.map_or(0, |task| task + 1);
+
+    if next_task < self.visible_tasks(self.selected_group) {
+      self.selected_task = Some(next_task);
+    } else if self.selected_group + 1 < self.groups.len() {
+      self.selected_group += 1;
+      self.selected_task = None;
+    }
+  }
+
+  /// Keeps the selection on an existing row after the list changed.
+  pub fn clamp_selection(&mut self) {
+    self.selected_group = self.selected_group.min(self.groups.len().saturating_sub(1));
+
+    let visible = self.visible_tasks(self.selected_group);
+    self.selected_task = self
+      .selected_task
+      .and_then(|task| (visible > 0).then(|| task.min(visible - 1)));
+  }
+
+  /// Index in the task pool of the selected task, if a task row is selected.
+  pub fn selected_task_index(&self) -> Option<usize> {
+    let group = self.groups.get(self.selected_group)?;
+
+    group.indices.get(self.selected_task?).copied()
+  }
+
+  /// Row of the selection in the task list.
+  pub fn selected_row(&self) -> usize {
+    let headers_before: usize = (0..self.selected_group)
+      .map(|group| 1 + self.visible_tasks(group))
+      .sum();
+
+    headers_before + self.selected_task.map_or(0, |task| task + 1)
+  }
+
+  /// Number of task rows shown under a group: none when it is collapsed.
+  fn visible_tasks(&self, group: usize) -> usize {
+    match self.groups.get(group) {
+      Some(group) if !self.collapsed_groups.contains(&group.name) => group.tasks.len(),
+      _ => 0,
+    }
+  }
+
+  /// Collapses or expands the selected group.
+  pub fn toggle_selected_group(&mut self) {
+    let Some(group) = self.groups.get(self.selected_group) else {
+      return;
+    };
+
+    if !self.collapsed_groups.remove(&group.name) {
+      self.collapsed_groups.insert(group.name.clone());
+      self.selected_task = None;
+    }
+  }
+
+  /// Switches the task list between grouping by config group and by file.
+  pub fn toggle_group_by(&mut self) {
+    self.group_by = self.group_by.toggled();
+    // Имена групп и файлов не связаны, поэтому свертка и выделение сбрасываются
+    self.collapsed_groups.clear();
+    self.selected_group = 0;
+    self.selected_task = None;
+  }
 }
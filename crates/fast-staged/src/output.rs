@@ -0,0 +1,108 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::Mutex;
+
+/// Default number of bytes kept per output stream.
+pub const DEFAULT_OUTPUT_LIMIT: usize = 64 * 1024;
+
+/// The last `limit` bytes of a command output stream.
+///
+/// Older bytes are dropped as new ones arrive, so memory stays bounded no
+/// matter how much a command prints.
+#[derive(Debug, Clone, Default)]
+pub struct OutputTail {
+  bytes: VecDeque<u8>,
+  limit: usize,
+  truncated: bool,
+  // Когда команда в последний раз что-то напечатала
+  last_output: Option<Instant>,
+}
+
+impl OutputTail {
+  pub fn new(limit: usize) -> Self {
+    Self {
+      bytes: VecDeque::new(),
+      limit,
+      truncated: false,
+      last_output: None,
+    }
+  }
+
+  pub fn push(&mut self, chunk: &[u8]) {
+    self.last_output = Some(Instant::now());
+    self.bytes.extend(chunk);
+
+    if self.bytes.len() > self.limit {
+      let excess = self.bytes.len() - self.limit;
+      self.bytes.drain(..excess);
+      self.truncated = true;
+    }
+  }
+
+  /// Drops the kept output, e.g. before a retry.
+  pub fn clear(&mut self) {
+    self.bytes.clear();
+    self.truncated = false;
+  }
+
+  /// Drops the lines `is_noise` returns true for, e.g. banners matched by `filter_output`.
+  pub fn remove_lines(&mut self, is_noise: impl Fn(&str) -> bool) {
+    let bytes: Vec<u8> = self.bytes.drain(..).collect();
+
+    for line in bytes.split_inclusive(|byte| *byte == b'\n') {
+      let text = String::from_utf8_lossy(line);
+
+      if !is_noise(text.trim_end_matches(['\n', '\r'])) {
+        self.bytes.extend(line);
+      }
+    }
+  }
+
+  pub fn last_output(&self) -> Option<Instant> {
+    self.last_output
+  }
+
+  /// Returns the kept output as text, marking where older output was dropped.
+  pub fn text(&self) -> String {
+    let (front, back) = self.bytes.as_slices();
+    let mut bytes = Vec::with_capacity(self.bytes.len());
+    bytes.extend_from_slice(front);
+    bytes.extend_from_slice(back);
+
+    let text = String::from_utf8_lossy(&bytes);
+
+    if self.truncated {
+      format!("[... output truncated ...]\n{}", text)
+    } else {
+      text.into_owned()
+    }
+  }
+}
+
+/// Reads `reader` to the end and returns everything, without a limit.
+pub async fn read_all<R: AsyncRead + Unpin>(reader: Option<R>) -> Vec<u8> {
+  let mut data = Vec::new();
+
+  if let Some(mut reader) = reader {
+    let _ = reader.read_to_end(&mut data).await;
+  }
+
+  data
+}
+
+/// Reads `reader` to the end, appending everything to `tail`.
+pub async fn capture<R: AsyncRead + Unpin>(reader: Option<R>, tail: &Mutex<OutputTail>) {
+  let Some(mut reader) = reader else {
+    return;
+  };
+
+  let mut buf = [0u8; 8192];
+
+  loop {
+    match reader.read(&mut buf).await {
+      Ok(0) | Err(_) => break,
+      Ok(n) => tail.lock().await.push(&buf[..n]),
+    }
+  }
+}
@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+/// Wall time spent in each phase of a run, printed with `--profile`.
+#[derive(Debug, Default)]
+pub struct Profile {
+  phases: Vec<(&'static str, Duration)>,
+}
+
+impl Profile {
+  /// Adds `duration` to `phase`; phases keep the order they were first recorded in.
+  pub fn record(&mut self, phase: &'static str, duration: Duration) {
+    match self.phases.iter_mut().find(|(name, _)| *name == phase) {
+      Some((_, total)) => *total += duration,
+      None => self.phases.push((phase, duration)),
+    }
+  }
+
+  /// Formats the breakdown as aligned `phase  time` lines.
+  pub fn lines(&self) -> Vec<String> {
+    let width = self
+      .phases
+      .iter()
+      .map(|(name, _)| name.len())
+      .max()
+      .unwrap_or(0);
+
+    self
+      .phases
+      .iter()
+      .map(|(name, duration)| {
+        format!(
+          "  {:<width$}  {:>8.1}ms",
+          name,
+          duration.as_secs_f64() * 1000.0,
+          width = width
+        )
+      })
+      .collect()
+  }
+}
@@ -0,0 +1,145 @@
+use crate::app::{AppError, Result};
+use crate::config::Config;
+use crate::file::{FileCommand, FileMatch, FilePatterns};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Future returned by [`FileProvider::changed_files`].
+pub type FilesFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<String>>> + Send + 'a>>;
+
+/// Future returned by [`FileProvider::changed_files_matched`].
+pub type MatchedFilesFuture<'a> =
+  Pin<Box<dyn Future<Output = Result<(Vec<String>, HashMap<String, FileMatch>)>> + Send + 'a>>;
+
+/// Source of the files to run commands on.
+pub trait FileProvider: Debug + Send + Sync {
+  /// Lists the files under `root`, reporting how many were found so far to `progress`.
+  ///
+  /// An empty list is returned as [`AppError::NoStagedFiles`].
+  fn changed_files<'a>(
+    &'a self,
+    root: &'a Path,
+    progress: Option<watch::Sender<usize>>,
+  ) -> FilesFuture<'a>;
+
+  /// Lists the files like [`FileProvider::changed_files`], matching them against
+  /// `patterns` along the way where the source can overlap the two.
+  ///
+  /// Files missing from the map are matched afterwards.
+  fn changed_files_matched<'a>(
+    &'a self,
+    root: &'a Path,
+    _patterns: Arc<FilePatterns>,
+    progress: Option<watch::Sender<usize>>,
+  ) -> MatchedFilesFuture<'a> {
+    Box::pin(async move { Ok((self.changed_files(root, progress).await?, HashMap::new())) })
+  }
+
+  /// Whether the files come from the git index of the current repository.
+  ///
+  /// Without it the run does not look for the repository root and skips
+  /// the steps that read the index: renames, ignored and binary files.
+  fn is_git_index(&self) -> bool {
+    true
+  }
+}
+
+/// Source of the config.
+pub trait ConfigProvider: Debug + Send + Sync {
+  /// Loads the config for the run rooted at `root`.
+  fn load(&self, root: &Path) -> Result<Config>;
+}
+
+/// Staged files from the git index.
+#[derive(Debug, Default)]
+pub struct GitIndex;
+
+impl FileProvider for GitIndex {
+  fn changed_files<'a>(
+    &'a self,
+    root: &'a Path,
+    progress: Option<watch::Sender<usize>>,
+  ) -> FilesFuture<'a> {
+    Box::pin(FileCommand::get_changed_files(root, progress))
+  }
+
+  fn changed_files_matched<'a>(
+    &'a self,
+    root: &'a Path,
+    patterns: Arc<FilePatterns>,
+    progress: Option<watch::Sender<usize>>,
+  ) -> MatchedFilesFuture<'a> {
+    Box::pin(FileCommand::get_changed_files_matched(
+      root,
+      Some(patterns),
+      progress,
+    ))
+  }
+}
+
+/// Files changed in the working tree but not staged (`--changed-only`).
+#[derive(Debug, Default)]
+pub struct WorkingTree;
+
+impl FileProvider for WorkingTree {
+  fn changed_files<'a>(
+    &'a self,
+    root: &'a Path,
+    progress: Option<watch::Sender<usize>>,
+  ) -> FilesFuture<'a> {
+    Box::pin(FileCommand::get_worktree_changes(root, progress))
+  }
+}
+
+/// A fixed list of files, no repository needed.
+#[derive(Debug, Default)]
+pub struct StaticFiles(pub Vec<String>);
+
+impl FileProvider for StaticFiles {
+  fn changed_files<'a>(
+    &'a self,
+    _root: &'a Path,
+    progress: Option<watch::Sender<usize>>,
+  ) -> FilesFuture<'a> {
+    Box::pin(async move {
+      if let Some(progress) = progress {
+        progress.send_replace(self.0.len());
+      }
+
+      if self.0.is_empty() {
+        return Err(AppError::NoStagedFiles);
+      }
+
+      Ok(self.0.clone())
+    })
+  }
+
+  fn is_git_index(&self) -> bool {
+    false
+  }
+}
+
+/// Config found on disk (or in `FAST_STAGED_CONFIG`), as in a normal run.
+#[derive(Debug, Default)]
+pub struct ConfigDiscovery;
+
+impl ConfigProvider for ConfigDiscovery {
+  fn load(&self, root: &Path) -> Result<Config> {
+    Config::load(root)
+  }
+}
+
+/// Config given as text: JSON when it starts with `{`, TOML otherwise.
+#[derive(Debug, Default)]
+pub struct InlineConfig(pub String);
+
+impl ConfigProvider for InlineConfig {
+  fn load(&self, _root: &Path) -> Result<Config> {
+    Config::from_content(Path::new("<inline>"), &self.0, None)
+  }
+}
@@ -0,0 +1,92 @@
+use std::io;
+#[cfg(unix)]
+use std::os::fd::{FromRawFd, OwnedFd};
+use std::process::Stdio;
+use tokio::io::AsyncRead;
+
+/// Size reported to commands running in a pseudo-terminal.
+const PTY_COLS: u16 = 120;
+const PTY_ROWS: u16 = 40;
+
+/// A pseudo-terminal pair for commands that misbehave without a TTY.
+///
+/// The command gets the slave side as stdin, stdout and stderr, the output
+/// is read from the master side.
+#[cfg(unix)]
+pub struct Pty {
+  master: OwnedFd,
+  slave: OwnedFd,
+}
+
+#[cfg(not(unix))]
+pub struct Pty;
+
+#[cfg(unix)]
+impl Pty {
+  pub fn open() -> io::Result<Self> {
+    let mut master = -1;
+    let mut slave = -1;
+    let mut size = libc::winsize {
+      ws_row: PTY_ROWS,
+      ws_col: PTY_COLS,
+      ws_xpixel: 0,
+      ws_ypixel: 0,
+    };
+
+    let result = unsafe {
+      libc::openpty(
+        &mut master,
+        &mut slave,
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        // На Linux указатель константный, на macOS изменяемый
+        &raw mut size,
+      )
+    };
+
+    if result != 0 {
+      return Err(io::Error::last_os_error());
+    }
+
+    // Дескрипторы только что созданы и больше никому не принадлежат
+    unsafe {
+      Ok(Self {
+        master: OwnedFd::from_raw_fd(master),
+        slave: OwnedFd::from_raw_fd(slave),
+      })
+    }
+  }
+
+  /// A handle to the slave side for one of the standard streams of the command.
+  pub fn stdio(&self) -> io::Result<Stdio> {
+    Ok(Stdio::from(self.slave.try_clone()?))
+  }
+
+  /// Closes the slave side and returns a reader for everything the command writes.
+  ///
+  /// Call it once the command is spawned: reading ends only when no process
+  /// holds the slave side open anymore.
+  pub fn into_reader(self) -> impl AsyncRead + Unpin {
+    drop(self.slave);
+
+    tokio::fs::File::from_std(std::fs::File::from(self.master))
+  }
+}
+
+#[cfg(not(unix))]
+impl Pty {
+  pub fn open() -> io::Result<Self> {
+    Err(io::Error::new(
+      io::ErrorKind::Unsupported,
+      "pseudo-terminals are only supported on unix",
+    ))
+  }
+
+  pub fn stdio(&self) -> io::Result<Stdio> {
+    Ok(Stdio::null())
+  }
+
+  pub fn into_reader(self) -> impl AsyncRead + Unpin {
+    tokio::io::empty()
+  }
+}
@@ -1,6 +1,7 @@
 use crossterm::{
   event::{DisableMouseCapture, EnableMouseCapture},
   execute,
+  style::Stylize,
   terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
 use ratatui::Frame;
@@ -10,32 +11,210 @@ use std::io;
 use ratatui::{
   backend::CrosstermBackend,
   prelude::*,
-  widgets::{Block, Borders, List, ListItem, Paragraph},
+  widgets::{Block, Borders, LineGauge, List, ListItem, ListState, Paragraph},
 };
 
-use crate::model::StateModel;
+use crate::config::{ExecutionOrder, Group, SortOrder, StatsSort};
+use crate::file::FileCommand;
+use crate::keymap::active_bindings;
+use crate::model::{QueueStats, StateModel};
+use crate::usage::ResourceUsage;
 
-fn render_title<'a>(statuses_len: &'a usize, total_files: &'a usize) -> Paragraph<'a> {
-  let title_text = format!(
-    "Running {} tasks for {} file(s)...",
-    statuses_len, total_files
-  );
+fn render_title<'a>(
+  statuses_len: &'a usize,
+  total_files: &'a usize,
+  status_rollup: &'a str,
+  modified_files: &'a Option<Vec<String>>,
+  scanning: bool,
+  budget: Option<(u128, u128)>,
+) -> Paragraph<'a> {
+  let mut title_text = if scanning {
+    format!("Scanning staged files... {} found", total_files)
+  } else {
+    format!(
+      "Running {} tasks for {} file(s)...",
+      statuses_len, total_files
+    )
+  };
+
+  if !status_rollup.is_empty() {
+    title_text.push_str(&format!(" {}", status_rollup));
+  }
+
+  // Обратный отсчет до `max_time`, после него - насколько бюджет превышен
+  if let Some((max_time, elapsed)) = budget
+    && !scanning
+  {
+    if elapsed <= max_time {
+      title_text.push_str(&format!(" ({}s left)", (max_time - elapsed).div_ceil(1000)));
+    } else {
+      title_text.push_str(&format!(" ({}s over budget)", (elapsed - max_time) / 1000));
+    }
+  }
+
+  if let Some(modified) = modified_files {
+    title_text.push_str(&format!("\nModified {} file(s)", modified.len()));
+  }
 
   Paragraph::new(title_text).block(Block::default().borders(Borders::empty()).title("Status"))
 }
 
-fn render_list<'a>(lines: &Vec<(String, Color, u128)>) -> List<'a> {
-  let items: Vec<ListItem> = lines
-    .iter()
-    .enumerate()
-    .map(|(_, (text, color, _))| {
-      ListItem::new(text.clone()).style(Style::default().fg(color.clone()))
-    })
-    .collect();
+/// Below this size the full layout does not fit and a single progress line is drawn.
+const COMPACT_WIDTH: u16 = 60;
+const COMPACT_HEIGHT: u16 = 12;
+
+/// Width of the right-aligned duration column in the task list.
+const DURATION_WIDTH: usize = 9;
+
+/// Durations below this are green, below `SLOW_TASK_MS` yellow, above it red.
+const FAST_TASK_MS: u128 = 1000;
+const SLOW_TASK_MS: u128 = 5000;
+
+fn duration_color(duration: u128) -> Color {
+  if duration < FAST_TASK_MS {
+    Color::Green
+  } else if duration < SLOW_TASK_MS {
+    Color::Yellow
+  } else {
+    Color::Red
+  }
+}
+
+/// Cells of the timeout bar a running task shows in the duration column.
+const TIMEOUT_BAR_WIDTH: usize = 5;
+
+/// The used share of a running task's timeout as a bar and a percentage,
+/// exactly as wide as the duration column.
+fn timeout_bar<'a>(share: f64) -> Vec<Span<'a>> {
+  let share = share.clamp(0.0, 1.0);
+  let filled = (share * TIMEOUT_BAR_WIDTH as f64).round() as usize;
+  let color = if share < 0.5 {
+    Color::Green
+  } else if share < 0.8 {
+    Color::Yellow
+  } else {
+    Color::Red
+  };
+
+  // Полоса из фона, а не из символов: видна и с `symbols = "ascii"`
+  vec![
+    Span::styled(" ".repeat(filled), Style::default().bg(color)),
+    Span::styled(
+      " ".repeat(TIMEOUT_BAR_WIDTH - filled),
+      Style::default().bg(Color::DarkGray),
+    ),
+    Span::styled(
+      format!(
+        "{:>width$}",
+        format!("{}%", (share * 100.0) as u32),
+        width = DURATION_WIDTH - TIMEOUT_BAR_WIDTH
+      ),
+      Style::default().fg(color),
+    ),
+  ]
+}
+
+/// A task line with its text padded or cut so the duration lands in a fixed right-hand column.
+///
+/// A running task with a timeout shows how much of it is used instead.
+fn task_line<'a>(
+  text: &str,
+  color: Color,
+  duration: u128,
+  timeout_share: Option<f64>,
+  width: usize,
+  ellipsis: &str,
+) -> Line<'a> {
+  let text_width = width.saturating_sub(DURATION_WIDTH + 1);
+  let mut text: String = format!("  {}", text);
+
+  if text.chars().count() > text_width {
+    let keep = text_width.saturating_sub(ellipsis.chars().count());
+    text = text.chars().take(keep).collect();
+    text.push_str(ellipsis);
+  }
+
+  let mut spans = vec![Span::styled(
+    format!("{:<width$} ", text, width = text_width),
+    Style::default().fg(color),
+  )];
+
+  // У еще не запущенных задач длительности нет
+  match timeout_share {
+    Some(share) if duration == 0 => spans.extend(timeout_bar(share)),
+    _ if duration > 0 => spans.push(Span::styled(
+      format!(
+        "{:>width$}",
+        format!("{}ms", duration),
+        width = DURATION_WIDTH
+      ),
+      Style::default().fg(duration_color(duration)),
+    )),
+    _ => spans.push(Span::raw(" ".repeat(DURATION_WIDTH))),
+  }
+
+  Line::from(spans)
+}
+
+fn render_list<'a>(model: &StateModel, width: u16) -> List<'a> {
+  let mut items: Vec<ListItem> = Vec::new();
+
+  for (idx, group) in model.groups.iter().enumerate() {
+    let collapsed = model.collapsed_groups.contains(&group.name);
+    let marker = if collapsed {
+      &model.symbols.collapsed
+    } else {
+      &model.symbols.expanded
+    };
+    let mut style = Style::default()
+      .fg(group.color)
+      .add_modifier(Modifier::BOLD);
+
+    if idx == model.selected_group {
+      style = style.add_modifier(Modifier::REVERSED);
+    }
+
+    items.push(ListItem::new(format!("{} {} ({})", marker, group.name, group.rollup)).style(style));
+
+    if collapsed {
+      continue;
+    }
+
+    for (task_idx, (text, color, duration, timeout_share)) in group.tasks.iter().enumerate() {
+      let mut item = ListItem::new(task_line(
+        text,
+        *color,
+        *duration,
+        *timeout_share,
+        width as usize,
+        &model.symbols.ellipsis,
+      ));
+
+      if idx == model.selected_group && model.selected_task == Some(task_idx) {
+        item = item.style(Style::default().add_modifier(Modifier::REVERSED));
+      }
+
+      items.push(item);
+    }
+  }
 
   List::new(items).block(Block::default().borders(Borders::empty()).title("Tasks"))
 }
 
+/// The last lines of a task's output that fit into `height`, below a titled border.
+fn render_output_pane<'a>(title: &str, text: &str, height: u16) -> Paragraph<'a> {
+  // Одна строка занята рамкой с заголовком
+  let visible = height.saturating_sub(1) as usize;
+  let lines: Vec<&str> = text.lines().collect();
+  let tail = lines[lines.len().saturating_sub(visible)..].join("\n");
+
+  Paragraph::new(tail).block(
+    Block::default()
+      .borders(Borders::TOP)
+      .title(format!("Output: {}", title)),
+  )
+}
+
 fn render_total_time<'a>(total_execution_time: &'a u128, elapsed_time: &'a u128) -> Paragraph<'a> {
   Paragraph::new(format!(
     "Total execution time: {}ms | Elapsed: {}ms",
@@ -44,20 +223,86 @@ fn render_total_time<'a>(total_execution_time: &'a u128, elapsed_time: &'a u128)
   .style(Style::default().fg(Color::White))
 }
 
-fn render_command_stats<'a>(command_stats: &'a HashMap<String, (usize, u128)>) -> Paragraph<'a> {
-  let mut stats_lines = Vec::new();
-  for (command, (count, total)) in command_stats {
-    let avg = if *count > 0 {
-      *total / (*count as u128)
-    } else {
-      0
+/// Gauge of running versus queued tasks, labelled with all three counts.
+fn render_queue<'a>(queue: &QueueStats) -> LineGauge<'a> {
+  LineGauge::default()
+    .ratio(queue.utilization())
+    .label(format!(
+      "{} running, {} queued, {} done ",
+      queue.running, queue.waiting, queue.finished
+    ))
+    .filled_style(Style::default().fg(Color::Green))
+    .unfilled_style(Style::default().fg(Color::DarkGray))
+}
+
+fn render_command_stats<'a>(
+  command_stats: &'a HashMap<String, (usize, u128)>,
+  command_usage: &'a HashMap<String, ResourceUsage>,
+  group_file_counts: &'a HashMap<String, usize>,
+  sort: StatsSort,
+  order: SortOrder,
+) -> Paragraph<'a> {
+  let mut group_lines: Vec<String> = group_file_counts
+    .iter()
+    .map(|(group, count)| format!("[{}] {} file(s)", group, count))
+    .collect();
+  group_lines.sort_by_key(|name| name.to_lowercase());
+
+  // (команда, число запусков, общее время, среднее время)
+  let mut stats: Vec<(&String, usize, u128, u128)> = command_stats
+    .iter()
+    .map(|(command, (count, total))| {
+      let avg = if *count > 0 {
+        *total / (*count as u128)
+      } else {
+        0
+      };
+      (command, *count, *total, avg)
+    })
+    .collect();
+
+  stats.sort_by(|a, b| {
+    let ordering = match sort {
+      StatsSort::Name => a.0.to_lowercase().cmp(&b.0.to_lowercase()),
+      StatsSort::Total => a.2.cmp(&b.2),
+      StatsSort::Avg => a.3.cmp(&b.3),
+      StatsSort::Count => a.1.cmp(&b.1),
     };
-    stats_lines.push(format!(
-      "{}: {} execution(s), total {}ms, avg {}ms",
-      command, count, total, avg
-    ));
+
+    let ordering = match order {
+      SortOrder::Asc => ordering,
+      SortOrder::Desc => ordering.reverse(),
+    };
+
+    // При равных значениях порядок не должен прыгать между кадрами
+    ordering.then_with(|| a.0.cmp(b.0))
+  });
+
+  let mut stats_lines: Vec<String> = stats
+    .into_iter()
+    .map(|(command, count, total, avg)| {
+      let mut line = format!(
+        "{}: {} execution(s), total {}ms, avg {}ms",
+        command, count, total, avg
+      );
+
+      // Колонка ресурсов есть только там, где платформа их сообщает
+      if let Some(usage) = command_usage.get(command) {
+        line.push_str(&format!(
+          ", cpu {}ms, peak {}",
+          usage.cpu_ms,
+          format_kb(usage.max_rss_kb)
+        ));
+      }
+
+      line
+    })
+    .collect();
+
+  if !group_lines.is_empty() {
+    stats_lines.insert(0, group_lines.join(", "));
   }
-  stats_lines.sort_by_key(|name| name.to_lowercase());
+
   let stats_text = stats_lines.join("\n");
 
   Paragraph::new(stats_text)
@@ -69,17 +314,92 @@ fn render_command_stats<'a>(command_stats: &'a HashMap<String, (usize, u128)>) -
     .style(Style::default().fg(Color::Cyan))
 }
 
-fn render_exit_message<'a>(running: &bool) -> Paragraph<'a> {
-  let text = if *running {
-    "Press `Esc`, `Ctrl-C` or `q` to stop running."
+fn format_kb(kb: u64) -> String {
+  if kb >= 1024 {
+    format!("{:.1}MB", kb as f64 / 1024.0)
   } else {
-    ""
+    format!("{}KB", kb)
+  }
+}
+
+fn render_slowest_tasks<'a>(slowest_tasks: &'a [(String, u128)]) -> List<'a> {
+  let items: Vec<ListItem> = slowest_tasks
+    .iter()
+    .enumerate()
+    .map(|(idx, (label, duration))| ListItem::new(format!("{}. {}ms {}", idx + 1, duration, label)))
+    .collect();
+
+  List::new(items)
+    .block(Block::default().borders(Borders::LEFT).title("Slowest"))
+    .style(Style::default().fg(Color::Yellow))
+}
+
+/// Key hints for the current screen, taken from the keymap the handler uses.
+fn render_key_bar<'a>(awaiting_dismiss: bool) -> Paragraph<'a> {
+  let mut spans = Vec::new();
+
+  if awaiting_dismiss {
+    spans.push(Span::raw("Finished. "));
+  }
+
+  for (idx, binding) in active_bindings(awaiting_dismiss).enumerate() {
+    if idx > 0 {
+      spans.push(Span::raw("  "));
+    }
+
+    spans.push(Span::styled(
+      binding.keys,
+      Style::default().add_modifier(Modifier::BOLD),
+    ));
+    spans.push(Span::styled(
+      format!(" {}", binding.description),
+      Style::default().fg(Color::DarkGray),
+    ));
+  }
+
+  Paragraph::new(Line::from(spans))
+}
+
+/// One progress line for small terminals: `[12/15] ⟳ running lint on app.ts`.
+fn render_compact<'a>(model: &StateModel, width: u16) -> Paragraph<'a> {
+  let queue = &model.queue;
+  let total = queue.waiting + queue.running + queue.finished;
+
+  let mut text = if model.scanning {
+    format!("Scanning staged files... {} found", model.total_files)
+  } else {
+    let progress = format!("[{}/{}]", queue.finished, total);
+
+    match &model.running_task {
+      Some(task) => format!("{} {} running {}", progress, model.symbols.running, task),
+      None if model.awaiting_dismiss => {
+        format!("{} {}, press any key", progress, model.status_rollup)
+      }
+      None => format!("{} {}", progress, model.status_rollup),
+    }
   };
 
-  Paragraph::new(text).block(Block::default().borders(Borders::empty()))
+  let width = usize::from(width);
+
+  if text.chars().count() > width {
+    let keep = width.saturating_sub(model.symbols.ellipsis.chars().count());
+    text = text.chars().take(keep).collect();
+    text.push_str(&model.symbols.ellipsis);
+  }
+
+  Paragraph::new(text)
 }
 
 pub fn render_frame<'a>(f: &mut Frame<'a>, model: &StateModel) {
+  let area = f.area();
+
+  if area.width < COMPACT_WIDTH || area.height < COMPACT_HEIGHT {
+    f.render_widget(render_compact(model, area.width), area);
+    return;
+  }
+
+  // Без панели статистики ее место отдается списку задач
+  let stats_height = if model.hide_stats { 0 } else { 3 };
   let areas = Layout::default()
     .direction(Direction::Vertical)
     .margin(1)
@@ -87,7 +407,9 @@ pub fn render_frame<'a>(f: &mut Frame<'a>, model: &StateModel) {
       [
         Constraint::Length(3),
         Constraint::Min(0),
-        Constraint::Length(3),
+        Constraint::Length(stats_height),
+        // Строка с подсказками по клавишам
+        Constraint::Length(1),
       ]
       .as_ref(),
     )
@@ -95,7 +417,16 @@ pub fn render_frame<'a>(f: &mut Frame<'a>, model: &StateModel) {
 
   // Заголовок с информацией о файлах
   f.render_widget(
-    render_title(&model.statuses_count, &model.total_files),
+    render_title(
+      &model.statuses_count,
+      &model.total_files,
+      &model.status_rollup,
+      &model.modified_files,
+      model.scanning,
+      model
+        .max_time
+        .map(|max_time| (max_time, model.elapsed_time)),
+    ),
     areas[0],
   );
 
@@ -104,23 +435,199 @@ pub fn render_frame<'a>(f: &mut Frame<'a>, model: &StateModel) {
     .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
     .split(areas[1]);
 
-  // Список задач
+  // Список задач и панель самых долгих задач справа
+  let list_areas = Layout::default()
+    .direction(Direction::Horizontal)
+    .constraints(if model.slowest_tasks.is_empty() {
+      [Constraint::Percentage(100), Constraint::Percentage(0)]
+    } else {
+      [Constraint::Percentage(70), Constraint::Percentage(30)]
+    })
+    .split(content_areas[0]);
+
+  // Под списком вывод выбранной задачи, обновляется на каждом тике
+  let list_areas = match &model.output_pane {
+    Some((title, text)) => {
+      let pane_areas = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+        .split(list_areas[0]);
+
+      f.render_widget(
+        render_output_pane(title, text, pane_areas[1].height),
+        pane_areas[1],
+      );
+
+      [pane_areas[0], list_areas[1]]
+    }
+    None => [list_areas[0], list_areas[1]],
+  };
+
   if !model.is_empty {
-    f.render_widget(render_list(&model.command_lines), content_areas[0]);
+    // Прокрутка считается заново на каждом кадре, чтобы выбранная группа
+    // оставалась видимой и после изменения размера терминала
+    let mut list_state = ListState::default().with_selected(Some(model.selected_row()));
+
+    f.render_stateful_widget(
+      render_list(model, list_areas[0].width),
+      list_areas[0],
+      &mut list_state,
+    );
+  }
+
+  if !model.slowest_tasks.is_empty() {
+    f.render_widget(render_slowest_tasks(&model.slowest_tasks), list_areas[1]);
   }
 
   // Статистика по командам
-  if !model.command_stats.is_empty() {
-    f.render_widget(render_command_stats(&model.command_stats), areas[2]);
+  if !model.hide_stats && !model.command_stats.is_empty() {
+    f.render_widget(
+      render_command_stats(
+        &model.command_stats,
+        &model.command_usage,
+        &model.group_file_counts,
+        model.stats_sort,
+        model.stats_order,
+      ),
+      areas[2],
+    );
   }
 
-  // Общее время выполнения команд
+  // Общее время выполнения команд и загрузка очереди справа
+  let footer_areas = Layout::default()
+    .direction(Direction::Horizontal)
+    .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+    .split(content_areas[1]);
+
   f.render_widget(
     render_total_time(&model.total_execution_time, &model.elapsed_time),
-    content_areas[1],
+    footer_areas[0],
   );
 
-  f.render_widget(render_exit_message(&model.running), areas[2])
+  if !model.is_empty {
+    f.render_widget(render_queue(&model.queue), footer_areas[1]);
+  }
+
+  f.render_widget(render_key_bar(model.awaiting_dismiss), areas[3])
+}
+
+/// Prints the final summary to stdout once the terminal is restored.
+pub fn print_summary(model: &StateModel) {
+  if !model.status_rollup.is_empty() {
+    println!("{}", model.status_rollup);
+  }
+
+  if let Some(modified) = &model.modified_files {
+    if modified.is_empty() {
+      println!("{}", "No files were modified".green());
+    } else {
+      println!(
+        "{}",
+        format!("Modified {} file(s):", modified.len()).yellow()
+      );
+
+      for file in modified {
+        println!("  {} {}", "M".yellow().bold(), file);
+      }
+    }
+  }
+}
+
+/// Formats groups as a table with one row per command, for `--list-groups`.
+pub fn groups_table(groups: &[Group]) -> String {
+  let mut rows: Vec<[String; 5]> = vec![[
+    "GROUP".to_string(),
+    "ORDER".to_string(),
+    "TIMEOUT".to_string(),
+    "PATTERN".to_string(),
+    "COMMAND".to_string(),
+  ]];
+
+  for group in groups {
+    let order = match group.execution_order {
+      ExecutionOrder::Parallel => "parallel",
+      ExecutionOrder::Sequential => "sequential",
+    };
+    let mut patterns: Vec<_> = group.patterns.iter().collect();
+    patterns.sort_by_key(|(pattern, _)| *pattern);
+
+    // Настройки группы и паттерн пишутся только в первой своей строке
+    let mut first_in_group = true;
+
+    for (pattern, commands) in patterns {
+      for (idx, command) in commands.iter().enumerate() {
+        let (name, order, timeout) = if first_in_group {
+          (
+            group.name.clone(),
+            order.to_string(),
+            group.timeout.clone().unwrap_or_else(|| "-".to_string()),
+          )
+        } else {
+          Default::default()
+        };
+        let pattern = if idx == 0 {
+          pattern.clone()
+        } else {
+          String::new()
+        };
+
+        rows.push([name, order, timeout, pattern, command.to_command_line()]);
+        first_in_group = false;
+      }
+    }
+  }
+
+  format_table(&rows)
+}
+
+/// Formats the commands that would run as a table, for `--dry-run`.
+pub fn dry_run_table(file_commands: &[FileCommand]) -> String {
+  let mut rows: Vec<[String; 4]> = vec![[
+    "GROUP".to_string(),
+    "FILE".to_string(),
+    "COMMAND".to_string(),
+    "SOURCE".to_string(),
+  ]];
+
+  for file_cmd in file_commands {
+    let source = if file_cmd.from_override {
+      "override"
+    } else {
+      "pattern"
+    };
+
+    rows.push([
+      file_cmd.group_name.clone(),
+      file_cmd.filename.clone(),
+      file_cmd.command.clone(),
+      source.to_string(),
+    ]);
+  }
+
+  format_table(&rows)
+}
+
+/// Left-aligns the cells of every column, the first row being the header.
+fn format_table<const N: usize>(rows: &[[String; N]]) -> String {
+  let mut widths = [0usize; N];
+  for row in rows {
+    for (width, cell) in widths.iter_mut().zip(row) {
+      *width = (*width).max(cell.chars().count());
+    }
+  }
+
+  let mut table = String::new();
+  for row in rows {
+    let line: Vec<String> = row
+      .iter()
+      .zip(widths)
+      .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+      .collect();
+    table.push_str(line.join("  ").trim_end());
+    table.push('\n');
+  }
+
+  table
 }
 
 pub fn setup_terminal() -> color_eyre::Result<ratatui::Terminal<CrosstermBackend<io::Stdout>>> {
@@ -0,0 +1,445 @@
+use crate::command::{CommandStatus, StatusDisplay, Symbols, status_rollup};
+use crate::task::{Task, TaskPool, spawn_error_reason};
+use crate::usage::ResourceUsage;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Structured result of a single task, suitable for machine consumption.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskReport {
+  pub filename: String,
+  pub command: String,
+  pub group: String,
+  pub status: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub error: Option<String>,
+  /// Why the command could not be started, e.g. "command not found" or "permission denied".
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub spawn_error: Option<String>,
+  pub duration_ms: u128,
+  /// Wall-clock start time in milliseconds since the Unix epoch.
+  pub started_at_ms: Option<u64>,
+  /// Wall-clock end time in milliseconds since the Unix epoch.
+  pub finished_at_ms: Option<u64>,
+  /// CPU time and peak memory of the command, unix only.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub usage: Option<ResourceUsage>,
+  /// Failed or timed out, taken from the status itself rather than its label.
+  #[serde(skip)]
+  pub failed: bool,
+}
+
+impl TaskReport {
+  pub async fn from_task(task: &Task) -> Self {
+    let status = task.get_status().await;
+    let error = match &status {
+      CommandStatus::Failed(msg) => Some(msg.clone()),
+      _ => None,
+    };
+
+    Self {
+      filename: task.filename.clone(),
+      command: task.command.clone(),
+      group: task.group_name.clone(),
+      status: status.to_string(),
+      error,
+      spawn_error: task.get_spawn_error().await.map(spawn_error_reason),
+      duration_ms: task.get_duration_ms().await,
+      started_at_ms: task.get_started_wall().await.map(epoch_ms),
+      finished_at_ms: task.get_finished_wall().await.map(epoch_ms),
+      usage: task.get_usage().await,
+      failed: status.is_failure(),
+    }
+  }
+}
+
+/// Results of the run in one repository of a `--repo` fan-out.
+#[derive(Debug, Clone, Serialize)]
+pub struct RepoReport {
+  pub repo: String,
+  pub tasks: Vec<TaskReport>,
+  /// Why the run failed, e.g. failed tasks or no config in this repository.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub error: Option<String>,
+}
+
+/// Counts of a finished run, so callers don't tally task statuses themselves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunSummary {
+  pub total: usize,
+  /// Done, with or without warnings.
+  pub passed: usize,
+  pub failed: usize,
+  pub timed_out: usize,
+  /// Skipped or cancelled: never ran to the end.
+  pub skipped: usize,
+  /// Wall time of the whole run in milliseconds.
+  pub wall_ms: u128,
+}
+
+impl RunSummary {
+  pub fn from_statuses(statuses: &[CommandStatus], wall_ms: u128) -> Self {
+    let mut summary = Self {
+      total: statuses.len(),
+      wall_ms,
+      ..Self::default()
+    };
+
+    for status in statuses {
+      match status {
+        CommandStatus::Done | CommandStatus::DoneWithWarnings => summary.passed += 1,
+        CommandStatus::Failed(_) => summary.failed += 1,
+        CommandStatus::Timeout => summary.timed_out += 1,
+        CommandStatus::Skipped | CommandStatus::Cancelled => summary.skipped += 1,
+        CommandStatus::Running | CommandStatus::Waiting | CommandStatus::None => {}
+      }
+    }
+
+    summary
+  }
+}
+
+/// Results of a headless run: every task and their totals.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunReport {
+  pub tasks: Vec<TaskReport>,
+  pub summary: RunSummary,
+}
+
+fn epoch_ms(time: SystemTime) -> u64 {
+  time
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_millis() as u64)
+    .unwrap_or(0)
+}
+
+/// A single task state transition in the progress stream.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+  Started {
+    filename: String,
+    command: String,
+    group: String,
+  },
+  Finished {
+    filename: String,
+    command: String,
+    group: String,
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    duration_ms: u128,
+  },
+  Done {
+    total_tasks: usize,
+    elapsed_ms: u128,
+  },
+}
+
+/// Turns snapshots of task statuses into a stream of transitions.
+#[derive(Debug, Default)]
+pub struct ProgressStream {
+  started: Vec<bool>,
+  finished: Vec<bool>,
+  done: bool,
+}
+
+impl ProgressStream {
+  /// Returns the events that happened since the previous call.
+  pub async fn observe(
+    &mut self,
+    task_pool: &TaskPool,
+    statuses: &[CommandStatus],
+    done: bool,
+    elapsed_ms: u128,
+  ) -> Vec<ProgressEvent> {
+    let mut events = Vec::new();
+
+    self.started.resize(statuses.len(), false);
+    self.finished.resize(statuses.len(), false);
+
+    for (idx, (state, status)) in task_pool.states.iter().zip(statuses).enumerate() {
+      let is_finished = status.is_finished();
+
+      // Быстрая задача может завершиться между двумя тиками, но `started` все равно нужен
+      if !self.started[idx] && (is_finished || *status == CommandStatus::Running) {
+        self.started[idx] = true;
+        events.push(ProgressEvent::Started {
+          filename: state.filename.clone(),
+          command: state.command.clone(),
+          group: state.group_name.clone(),
+        });
+      }
+
+      if !self.finished[idx] && is_finished {
+        self.finished[idx] = true;
+        events.push(ProgressEvent::Finished {
+          filename: state.filename.clone(),
+          command: state.command.clone(),
+          group: state.group_name.clone(),
+          status: status.to_string(),
+          error: match status {
+            CommandStatus::Failed(msg) => Some(msg.clone()),
+            _ => None,
+          },
+          duration_ms: state.get_duration_ms().await,
+        });
+      }
+    }
+
+    if done && !self.done {
+      self.done = true;
+      events.push(ProgressEvent::Done {
+        total_tasks: statuses.len(),
+        elapsed_ms,
+      });
+    }
+
+    events
+  }
+}
+
+/// Line-oriented output for non-interactive runs (CI logs, pipes).
+///
+/// Every finished task is printed at once, with each line prefixed by
+/// `[group:file]` so output from parallel tasks stays attributable.
+#[derive(Debug, Default)]
+pub struct LineStream {
+  printed: Vec<bool>,
+  symbols: Symbols,
+}
+
+impl LineStream {
+  pub fn new(symbols: Symbols) -> Self {
+    Self {
+      printed: Vec::new(),
+      symbols,
+    }
+  }
+
+  /// Returns the lines for tasks that finished since the previous call.
+  pub async fn observe(&mut self, task_pool: &TaskPool, statuses: &[CommandStatus]) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    self.printed.resize(statuses.len(), false);
+
+    for (idx, (state, status)) in task_pool.states.iter().zip(statuses).enumerate() {
+      if self.printed[idx] || !status.is_finished() {
+        continue;
+      }
+
+      self.printed[idx] = true;
+
+      let prefix = format!("[{}:{}]", state.group_name, state.filename);
+      let (symbol, _) = status.colored(&self.symbols);
+      let mut header = format!(
+        "{} {} {} ({}ms)",
+        prefix,
+        symbol,
+        state.command,
+        state.get_duration_ms().await
+      );
+
+      if let CommandStatus::Failed(msg) = status {
+        header.push_str(&format!(" - {}", msg));
+      }
+
+      lines.push(header);
+
+      for output in [&state.stdout, &state.stderr] {
+        let text = output.lock().await.text();
+
+        for line in text.lines() {
+          lines.push(format!("{} {}", prefix, line));
+        }
+      }
+    }
+
+    lines
+  }
+}
+
+/// Default pattern for `file:line[:col]: message` diagnostics, the format most linters print.
+pub const DEFAULT_ANNOTATION_PATTERN: &str =
+  r"^(?P<file>[^\s:]+):(?P<line>\d+)(?::(?P<col>\d+))?:?\s+(?P<message>.+)$";
+
+/// Whether the run happens inside GitHub Actions.
+pub fn in_github_actions() -> bool {
+  std::env::var("GITHUB_ACTIONS").is_ok_and(|value| value == "true")
+}
+
+/// GitHub Actions workflow commands for failed tasks and tasks with warnings.
+///
+/// Output lines matching `pattern` (named groups `file`, `line`, `col` and `message`)
+/// become inline annotations. A failed task without such lines still gets one
+/// annotation on its file.
+pub async fn github_annotations(task_pool: &TaskPool, pattern: &Regex) -> Vec<String> {
+  let mut annotations = Vec::new();
+
+  for state in &task_pool.states {
+    let status = state.get_status().await;
+    let level = match status {
+      CommandStatus::Failed(_) | CommandStatus::Timeout => "error",
+      CommandStatus::DoneWithWarnings => "warning",
+      _ => continue,
+    };
+
+    let output = format!(
+      "{}\n{}",
+      state.stdout.lock().await.text(),
+      state.stderr.lock().await.text()
+    );
+    let mut found = false;
+
+    for line in output.lines() {
+      let Some(caps) = pattern.captures(line) else {
+        continue;
+      };
+
+      let mut properties = Vec::new();
+
+      if let Some(file) = caps.name("file") {
+        properties.push(format!("file={}", escape_property(file.as_str())));
+      }
+
+      for name in ["line", "col"] {
+        if let Some(value) = caps.name(name) {
+          properties.push(format!("{}={}", name, value.as_str()));
+        }
+      }
+
+      let message = caps
+        .name("message")
+        .map_or(line, |message| message.as_str());
+
+      annotations.push(format!(
+        "::{} {}::{}",
+        level,
+        properties.join(","),
+        escape_data(message)
+      ));
+      found = true;
+    }
+
+    if !found && level == "error" {
+      // У команды на всю группу вместо имени файла подпись, к ней аннотацию не привязать
+      let file = if state.files.is_empty() {
+        format!("file={},", escape_property(&state.filename))
+      } else {
+        String::new()
+      };
+
+      annotations.push(format!(
+        "::error {}title={}::{}",
+        file,
+        escape_property(&state.command),
+        escape_data(&status.to_string())
+      ));
+    }
+  }
+
+  annotations
+}
+
+fn escape_data(value: &str) -> String {
+  value
+    .replace('%', "%25")
+    .replace('\r', "%0D")
+    .replace('\n', "%0A")
+}
+
+fn escape_property(value: &str) -> String {
+  escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Builds a self-contained HTML page with every task and its captured output.
+pub async fn html_report(task_pool: &TaskPool) -> String {
+  let statuses = task_pool.statuses().await;
+  let mut rows = String::new();
+
+  for (state, status) in task_pool.states.iter().zip(&statuses) {
+    let report = TaskReport::from_task(state).await;
+    let stdout = state.stdout.lock().await.text();
+    let stderr = state.stderr.lock().await.text();
+    let output = match (stdout.trim().is_empty(), stderr.trim().is_empty()) {
+      (_, true) => stdout,
+      (true, false) => stderr,
+      (false, false) => format!("{}\n{}", stdout.trim_end(), stderr),
+    };
+
+    let class = match status {
+      CommandStatus::Failed(_) | CommandStatus::Timeout => "failed",
+      CommandStatus::DoneWithWarnings => "warning",
+      CommandStatus::Done => "done",
+      _ => "other",
+    };
+    let status_text = match &report.error {
+      Some(error) => format!("{} ({})", report.status, error),
+      None => report.status.clone(),
+    };
+    // Вывод свернут, у упавших задач раскрыт сразу
+    let details = if output.trim().is_empty() {
+      String::new()
+    } else {
+      format!(
+        "<details{}><summary>output</summary><pre>{}</pre></details>",
+        if class == "failed" { " open" } else { "" },
+        escape_html(output.trim_end())
+      )
+    };
+
+    rows.push_str(&format!(
+      "<tr class=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td><code>{}</code>{}</td><td class=\"num\">{}ms</td></tr>\n",
+      class,
+      escape_html(&status_text),
+      escape_html(&report.group),
+      escape_html(&report.filename),
+      escape_html(&report.command),
+      details,
+      report.duration_ms
+    ));
+  }
+
+  format!(
+    r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>fast-staged report</title>
+<style>
+body {{ font-family: system-ui, sans-serif; margin: 2em; }}
+table {{ border-collapse: collapse; width: 100%; }}
+th, td {{ border-bottom: 1px solid #ddd; padding: 4px 8px; text-align: left; vertical-align: top; }}
+td.num {{ text-align: right; white-space: nowrap; }}
+tr.failed td:first-child {{ color: #c62828; }}
+tr.warning td:first-child {{ color: #ef6c00; }}
+tr.done td:first-child {{ color: #2e7d32; }}
+pre {{ background: #f6f8fa; padding: 8px; overflow-x: auto; }}
+</style>
+</head>
+<body>
+<h1>fast-staged report</h1>
+<p>{} task(s): {}</p>
+<table>
+<thead><tr><th>Status</th><th>Group</th><th>File</th><th>Command</th><th>Duration</th></tr></thead>
+<tbody>
+{}</tbody>
+</table>
+</body>
+</html>
+"#,
+    statuses.len(),
+    escape_html(&status_rollup(&statuses)),
+    rows
+  )
+}
+
+fn escape_html(value: &str) -> String {
+  value
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
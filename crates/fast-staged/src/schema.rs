@@ -0,0 +1,352 @@
+use crate::validate::ENUM_VALUES;
+use serde_json::{Map, Value, json};
+
+/// Builds a JSON Schema for the config file, for editor completion and validation.
+///
+/// Written by hand to follow the serde structures in `config`: keep it in sync
+/// when options are added.
+pub fn config_schema() -> Value {
+  let mut options = Map::new();
+
+  options.insert(
+    "timeout".to_string(),
+    duration("Timeout for every command, e.g. \"30s\""),
+  );
+  options.insert(
+    "kill_grace".to_string(),
+    duration("Pause between SIGTERM and SIGKILL for timed out commands (default 2s)"),
+  );
+  options.insert(
+    "output_limit_kb".to_string(),
+    integer("How many kilobytes of the latest output to keep per command (default 64)"),
+  );
+  options.insert("nice".to_string(), nice());
+  options.insert(
+    "command_prefix".to_string(),
+    command_prefix(
+      "Program and arguments every command is wrapped in, e.g. [\"nix\", \"develop\", \"-c\"]",
+    ),
+  );
+  options.insert(
+    "execution_order".to_string(),
+    enumeration("execution_order", "Order of commands within a group"),
+  );
+  options.insert(
+    "strict_coverage".to_string(),
+    boolean("Fail when a staged file matches no group"),
+  );
+  options.insert(
+    "strict_patterns".to_string(),
+    boolean("Fail instead of warning when a pattern is defined in several groups"),
+  );
+  options.insert(
+    "skip_ignored".to_string(),
+    boolean("Skip staged files matched by .gitignore unless --force is given"),
+  );
+  options.insert(
+    "renames".to_string(),
+    enumeration("renames", "Which path of a renamed file to run commands on"),
+  );
+  options.insert(
+    "annotation_pattern".to_string(),
+    string("Regex with named groups file, line, col and message for GitHub Actions annotations"),
+  );
+  options.insert(
+    "html_report".to_string(),
+    string("Path to write an HTML report of the run to, relative to the repository root"),
+  );
+  options.insert(
+    "wait_on_exit".to_string(),
+    enumeration(
+      "wait_on_exit",
+      "What to do with the TUI once all tasks are finished",
+    ),
+  );
+  options.insert(
+    "linger_ms".to_string(),
+    integer("How long the final frame stays on screen in \"timed\" mode"),
+  );
+  options.insert(
+    "redraw_interval_ms".to_string(),
+    integer("Minimum pause between TUI frames when many tasks finish at once (default 30)"),
+  );
+  options.insert(
+    "max_time".to_string(),
+    duration("Time budget for the run, shown as a countdown in the TUI title"),
+  );
+  options.insert(
+    "task_sort".to_string(),
+    enumeration("task_sort", "Order of tasks within each group"),
+  );
+  options.insert(
+    "group_by".to_string(),
+    enumeration("group_by", "Group the task list by config group or by file"),
+  );
+  options.insert(
+    "show_stats".to_string(),
+    boolean("Show the command statistics panel (default true)"),
+  );
+  options.insert(
+    "stats_sort".to_string(),
+    enumeration("stats_sort", "Sort key for the command statistics panel"),
+  );
+  options.insert(
+    "stats_order".to_string(),
+    enumeration(
+      "stats_order",
+      "Sort direction for the command statistics panel",
+    ),
+  );
+  options.insert(
+    "on_empty".to_string(),
+    enumeration("on_empty", "What to do when no staged file has commands"),
+  );
+  options.insert(
+    "on_missing_command".to_string(),
+    enumeration(
+      "on_missing_command",
+      "What to do when a command is not found in PATH",
+    ),
+  );
+  options.insert(
+    "fail_on_empty_commit".to_string(),
+    boolean("Fail when nothing is staged (default true)"),
+  );
+  options.insert("symbols".to_string(), symbols());
+  options.insert(
+    "$schema".to_string(),
+    string("Path or URL of this schema, for editors"),
+  );
+  options.insert(
+    "overrides".to_string(),
+    json!({
+      "description": "Commands for exact paths that win over group patterns",
+      "type": "object",
+      "additionalProperties": {
+        "type": "object",
+        "properties": {
+          "commands": { "$ref": "#/$defs/patternCommands" },
+          "append": boolean("Add the commands to the group commands instead of replacing them"),
+        },
+        "required": ["commands"],
+        "additionalProperties": false,
+      },
+    }),
+  );
+  options.insert(
+    "commands".to_string(),
+    json!({
+      "description": "Named commands referenced from patterns as \"@name\"",
+      "type": "object",
+      "additionalProperties": { "$ref": "#/$defs/command" },
+    }),
+  );
+
+  json!({
+    "$schema": "https://json-schema.org/draft/2020-12/schema",
+    "title": "fast-staged config",
+    "type": "object",
+    "properties": options,
+    // Любой другой ключ - имя группы
+    "additionalProperties": { "$ref": "#/$defs/group" },
+    "$defs": {
+      "group": group(),
+      "patternCommands": {
+        "description": "Commands for a pattern: one list, or separate lists per --mode",
+        "oneOf": [
+          { "$ref": "#/$defs/commandList" },
+          {
+            "type": "object",
+            "properties": {
+              "fix": { "$ref": "#/$defs/commandList" },
+              "check": { "$ref": "#/$defs/commandList" },
+            },
+            "additionalProperties": false,
+          },
+        ],
+      },
+      "commandList": {
+        "type": "array",
+        "items": { "$ref": "#/$defs/command" },
+        "minItems": 1,
+      },
+      "command": {
+        "oneOf": [
+          {
+            "description": "Shell command, {file}, {files} and {ranges} are substituted",
+            "type": "string",
+          },
+          {
+            "description": "Program and arguments, executed without a shell",
+            "type": "array",
+            "items": { "type": "string" },
+            "minItems": 1,
+          },
+          {
+            "type": "object",
+            "properties": {
+              "run": { "$ref": "#/$defs/command" },
+              "continue_on_error": boolean("A failure does not stop a group with stop_on_error"),
+              "stdin": {
+                "description": "\"file\" pipes the file to stdin and writes stdout back to it on success",
+                "enum": ["none", "file"],
+              },
+              "success_codes": {
+                "description": "Exit codes that count as success instead of only 0, e.g. [0, 1]",
+                "type": "array",
+                "items": { "type": "integer" },
+              },
+            },
+            "required": ["run"],
+            "additionalProperties": false,
+          },
+          {
+            "type": "object",
+            "properties": {
+              "chain": {
+                "description": "Steps that run one after another",
+                "type": "array",
+                "items": { "$ref": "#/$defs/command" },
+              },
+            },
+            "required": ["chain"],
+            "additionalProperties": false,
+          },
+        ],
+      },
+    },
+  })
+}
+
+fn group() -> Value {
+  json!({
+    "type": "object",
+    "properties": {
+      "timeout": duration("Timeout for the commands of the group"),
+      "kill_grace": duration("Pause between SIGTERM and SIGKILL for the group"),
+      "execution_order": enumeration("execution_order", "Order of commands within the group"),
+      "mutex": string("Groups with the same label never run at the same time"),
+      "only_changed_lines": boolean("Run only for files with changed lines and pass them as {ranges}"),
+      "env": {
+        "description": "Environment variables for the commands, values support ${VAR}",
+        "type": "object",
+        "additionalProperties": { "type": "string" },
+      },
+      "clean_env": {
+        "description": "Run commands with only PATH and the listed variables instead of the inherited environment, true for PATH only",
+        "oneOf": [
+          { "type": "boolean" },
+          { "type": "array", "items": { "type": "string" } },
+        ],
+      },
+      "warning_pattern": string("Regex for stdout that marks a successful command as done with warnings"),
+      "fail_on_output": {
+        "description": "Fail a successful command whose stdout or stderr matches this regex, true for any output",
+        "type": ["string", "boolean"],
+      },
+      "filter_output": {
+        "description": "Regexes for output lines to drop before display and reports, e.g. deprecation banners",
+        "type": "array",
+        "items": { "type": "string" },
+      },
+      "capture": string("Regex for the file path, its named groups are substituted as {name} in commands"),
+      "stop_on_error": boolean("In a sequential group, skip the remaining commands after a failure"),
+      "group_fail_fast": boolean("In a parallel group, cancel the other running commands of the group after a failure"),
+      "output_limit_kb": integer("How many kilobytes of output to keep per command"),
+      "priority": {
+        "description": "Groups with a lower priority finish before the next ones start",
+        "type": "integer",
+      },
+      "scope": enumeration("scope", "Run commands per file or once for all matched files"),
+      "binary": boolean("Run commands for binary files too"),
+      "tty": boolean("Run commands in a pseudo-terminal, with stdout and stderr combined"),
+      "retry_on": enumeration("retry_on", "Which outcomes of a command are retried"),
+      "max_failures": integer("How many failed tasks of the group are tolerated before the run fails"),
+      "timeout_clock": enumeration("timeout_clock", "Measure the timeout in wall time or in CPU time of the command"),
+      "retries": integer("How many times a command is retried with retry_on (default 1)"),
+      "fail_on_changes": boolean("Fail a command that modified its files, e.g. a formatter in check mode"),
+      "nice": nice(),
+      "command_prefix": command_prefix("Wrapper for the commands of the group instead of the global one, [] disables it"),
+      "when": string("Shell command that must succeed for the group to run, otherwise its tasks are skipped"),
+      "absolute_paths": boolean("Pass absolute paths in {file} and {files} instead of repo-relative ones"),
+      "patterns": {
+        "description": "Glob patterns and their commands",
+        "type": "object",
+        "additionalProperties": { "$ref": "#/$defs/patternCommands" },
+      },
+    },
+    "required": ["patterns"],
+    "additionalProperties": false,
+  })
+}
+
+fn symbols() -> Value {
+  let mut custom = Map::new();
+  custom.insert(
+    "preset".to_string(),
+    json!({ "enum": ["unicode", "ascii"] }),
+  );
+
+  for name in [
+    "done",
+    "warning",
+    "failed",
+    "running",
+    "waiting",
+    "timeout",
+    "skipped",
+    "cancelled",
+    "expanded",
+    "collapsed",
+    "ellipsis",
+  ] {
+    custom.insert(name.to_string(), json!({ "type": "string" }));
+  }
+
+  json!({
+    "description": "Status symbols: a preset or a table overriding single symbols",
+    "oneOf": [
+      { "enum": ["unicode", "ascii"] },
+      { "type": "object", "properties": custom, "additionalProperties": false },
+    ],
+  })
+}
+
+fn enumeration(key: &str, description: &str) -> Value {
+  let values = ENUM_VALUES
+    .iter()
+    .find(|(name, _)| *name == key)
+    .map(|(_, values)| *values)
+    .unwrap_or_default();
+
+  json!({ "description": description, "enum": values })
+}
+
+fn nice() -> Value {
+  json!({
+    "description": "Lower scheduling priority of commands, like nice (1-19)",
+    "type": "integer",
+    "minimum": 0,
+    "maximum": 19,
+  })
+}
+
+fn command_prefix(description: &str) -> Value {
+  json!({ "description": description, "type": "array", "items": { "type": "string" } })
+}
+
+fn duration(description: &str) -> Value {
+  json!({ "description": description, "type": "string", "examples": ["30s", "2m"] })
+}
+
+fn string(description: &str) -> Value {
+  json!({ "description": description, "type": "string" })
+}
+
+fn integer(description: &str) -> Value {
+  json!({ "description": description, "type": "integer", "minimum": 0 })
+}
+
+fn boolean(description: &str) -> Value {
+  json!({ "description": description, "type": "boolean" })
+}
@@ -0,0 +1,155 @@
+use crate::app::AppError;
+use crate::app::Result;
+use gix::ObjectId;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Blob hashes of the staged files at a given moment.
+///
+/// Used to find out which files were rewritten by the commands.
+#[derive(Debug, Default, Clone)]
+pub struct FileSnapshot {
+  // Каталог, от которого считаются пути файлов
+  root: PathBuf,
+  hashes: HashMap<String, Option<ObjectId>>,
+}
+
+impl FileSnapshot {
+  /// Hashes every file from `files`, relative to `root`, the same way git would hash it as a blob.
+  pub async fn capture(root: &Path, files: &[String]) -> Result<Self> {
+    let dir = root.to_path_buf();
+    let files = files.to_vec();
+
+    let hashes =
+      tokio::task::spawn_blocking(move || -> Result<HashMap<String, Option<ObjectId>>> {
+        // Вне репозитория (файлы переданы напрямую) хватает SHA-1
+        let hash_kind = gix::open(&dir)
+          .map(|repo| repo.object_hash())
+          .unwrap_or(gix::hash::Kind::Sha1);
+        let mut hashes = HashMap::new();
+
+        for file in files {
+          let hash = hash_file(hash_kind, &dir.join(&file))?;
+          hashes.insert(file, hash);
+        }
+
+        Ok(hashes)
+      })
+      .await??;
+
+    Ok(Self {
+      root: root.to_path_buf(),
+      hashes,
+    })
+  }
+
+  /// Takes the blob ids of `files` from the index of the repository at `root`.
+  ///
+  /// Only a file whose stat no longer matches its entry (an unstaged edit) is
+  /// read and hashed, so the snapshot matches what the commands will see.
+  pub async fn from_index(root: &Path, files: &[String]) -> Result<Self> {
+    let dir = root.to_path_buf();
+    let files = files.to_vec();
+
+    let hashes =
+      tokio::task::spawn_blocking(move || -> Result<HashMap<String, Option<ObjectId>>> {
+        let repo = gix::open(&dir).map_err(|_| AppError::NotGitRepository { dir: dir.clone() })?;
+        let index = repo
+          .index()
+          .map_err(|e| AppError::GitError(format!("{}", e)))?;
+        let hash_kind = repo.object_hash();
+        let options = gix::index::entry::stat::Options::default();
+        let wanted: HashSet<&str> = files.iter().map(String::as_str).collect();
+        let mut hashes = HashMap::with_capacity(files.len());
+
+        for entry in index.entries() {
+          if entry.stage() != gix::index::entry::Stage::Unconflicted {
+            continue;
+          }
+
+          let path = entry.path(&index).to_string();
+
+          if !wanted.contains(path.as_str()) {
+            continue;
+          }
+
+          // Запись, обновленная в ту же секунду, что и индекс, ненадежна: ее читаем с диска
+          let unchanged = gix::index::fs::Metadata::from_path_no_follow(&dir.join(&path))
+            .ok()
+            .and_then(|meta| gix::index::entry::Stat::from_fs(&meta).ok())
+            .is_some_and(|stat| {
+              stat.matches(&entry.stat, options) && !entry.stat.is_racy(index.timestamp(), options)
+            });
+
+          let hash = if unchanged {
+            Some(entry.id)
+          } else {
+            hash_file(hash_kind, &dir.join(&path))?
+          };
+
+          hashes.insert(path, hash);
+        }
+
+        // Файлы не из индекса (например, удаленные из него) хешируем как есть
+        for file in &files {
+          if !hashes.contains_key(file) {
+            let hash = hash_file(hash_kind, &dir.join(file))?;
+            hashes.insert(file.clone(), hash);
+          }
+        }
+
+        Ok(hashes)
+      })
+      .await??;
+
+    Ok(Self {
+      root: root.to_path_buf(),
+      hashes,
+    })
+  }
+
+  /// Returns the hex blob hash of `file`, if it existed when the snapshot was taken.
+  pub fn hash(&self, file: &str) -> Option<String> {
+    self
+      .hashes
+      .get(file)
+      .and_then(|hash| hash.as_ref())
+      .map(|hash| hash.to_string())
+  }
+
+  /// Returns the files among `files` whose content differs from this snapshot, sorted by path.
+  ///
+  /// Only these files are read again; files the snapshot doesn't know are ignored.
+  pub async fn modified_files(&self, files: &[String]) -> Result<Vec<String>> {
+    let files: Vec<String> = files
+      .iter()
+      .filter(|file| self.hashes.contains_key(*file))
+      .cloned()
+      .collect::<BTreeSet<_>>()
+      .into_iter()
+      .collect();
+    let current = Self::capture(&self.root, &files).await?;
+
+    let mut modified: Vec<String> = current
+      .hashes
+      .iter()
+      .filter(|(file, hash)| self.hashes.get(*file) != Some(*hash))
+      .map(|(file, _)| file.clone())
+      .collect();
+
+    modified.sort();
+
+    Ok(modified)
+  }
+}
+
+/// Hashes the file at `path` as a git blob; a missing file is absent, not an error.
+fn hash_file(kind: gix::hash::Kind, path: &Path) -> Result<Option<ObjectId>> {
+  let Ok(data) = std::fs::read(path) else {
+    return Ok(None);
+  };
+
+  gix::objs::compute_hash(kind, gix::objs::Kind::Blob, &data)
+    .map(Some)
+    .map_err(|e| AppError::GitError(format!("{}", e)))
+}
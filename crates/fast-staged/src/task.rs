@@ -1,21 +1,53 @@
 use crate::app::AppError;
 use crate::app::Result;
-use crate::command::{CommandStatus, StatusDisplay};
-use crate::config::ExecutionOrder;
-use crate::file::FileCommand;
+use crate::command::{CommandStatus, StatusDisplay, Symbols, status_rollup};
+use crate::config::{
+  ExecutionOrder, GroupBy, OnMissingCommand, RetryOn, StdinMode, TaskSort, TimeoutClock,
+};
+use crate::diff::LineRange;
+use crate::file::{FileCommand, shell_quote};
+use crate::model::{GroupView, TaskLine};
+use crate::output::{DEFAULT_OUTPUT_LIMIT, OutputTail, capture, read_all};
+use crate::pty::Pty;
+use crate::report::{RunSummary, TaskReport};
+use crate::snapshot::FileSnapshot;
+use crate::usage::{Process, ResourceUsage, process_group_cpu_time};
 use ratatui::style::Color;
-use std::collections::HashMap;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::process::Stdio;
 use std::sync::Arc;
 use std::time::Duration;
 use std::time::Instant;
+use std::time::SystemTime;
+use tokio::io::AsyncWriteExt;
 use tokio::process::Command;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Notify};
 use tokio::task::JoinSet;
+use tokio::time::timeout;
+
+/// Set for every spawned command so a nested fast-staged run can detect the recursion.
+pub const RUNNING_ENV: &str = "FAST_STAGED_RUNNING";
+
+/// How long a running task may stay silent before it is flagged as possibly stuck.
+const SILENCE_WARNING: Duration = Duration::from_secs(10);
+
+/// Pending run of one group's commands.
+type GroupRun = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Default pause between SIGTERM and SIGKILL for timed out commands.
+const DEFAULT_KILL_GRACE: Duration = Duration::from_secs(2);
 
 #[derive(Debug)]
 pub struct TaskPool {
   pub states: Vec<Task>,
   pub join_set: JoinSet<()>,
+  /// Signaled whenever a task changes status, so the UI redraws without waiting for a tick.
+  pub changed: Arc<Notify>,
+  // (файл, команда, группа) уже добавленных задач
+  keys: HashSet<(String, String, String)>,
 }
 
 impl TaskPool {
@@ -23,10 +55,25 @@ impl TaskPool {
     Self {
       states: Vec::new(),
       join_set: JoinSet::new(),
+      changed: Arc::new(Notify::new()),
+      keys: HashSet::new(),
     }
   }
 
-  pub fn add(&mut self, state: Task) {
+  pub fn add(&mut self, mut state: Task) {
+    let inserted = self.keys.insert((
+      state.filename.clone(),
+      state.command.clone(),
+      state.group_name.clone(),
+    ));
+
+    debug_assert!(
+      inserted,
+      "duplicate task for {}: {}",
+      state.filename, state.command
+    );
+
+    state.changed = self.changed.clone();
     self.states.push(state);
   }
 
@@ -34,6 +81,27 @@ impl TaskPool {
     self.states.is_empty()
   }
 
+  /// Number of tasks, one per distinct file and command pair.
+  pub fn task_count(&self) -> usize {
+    self.states.len()
+  }
+
+  /// Number of distinct files that have at least one task.
+  pub fn file_count(&self) -> usize {
+    self
+      .states
+      .iter()
+      .flat_map(|task| {
+        if task.files.is_empty() {
+          vec![task.filename.as_str()]
+        } else {
+          task.files.iter().map(String::as_str).collect()
+        }
+      })
+      .collect::<HashSet<_>>()
+      .len()
+  }
+
   pub fn get_states(&self) -> &Vec<Task> {
     &self.states
   }
@@ -54,34 +122,188 @@ impl TaskPool {
     command_stats
   }
 
-  pub async fn get_command_list(&self) -> Vec<(String, Color, u128)> {
+  /// CPU time summed and peak memory maxed per command, for tasks with known usage.
+  pub async fn get_command_usage(&self) -> HashMap<String, ResourceUsage> {
+    let mut command_usage: HashMap<String, ResourceUsage> = HashMap::new();
+
+    for state in &self.states {
+      let Some(usage) = state.get_usage().await else {
+        continue;
+      };
+
+      let entry = command_usage.entry(state.command.clone()).or_default();
+      entry.cpu_ms += usage.cpu_ms;
+      entry.max_rss_kb = entry.max_rss_kb.max(usage.max_rss_kb);
+    }
+
+    command_usage
+  }
+
+  pub async fn get_command_list(&self, symbols: &Symbols, group_by: GroupBy) -> Vec<TaskLine> {
     let statuses = &self.statuses().await;
     let durations = &self.durations().await;
 
-    self
-      .get_states()
-      .iter()
-      .enumerate()
-      .map(|(idx, state)| {
-        let status = &statuses[idx];
-        let duration = durations[idx];
-        let (symbol, color) = status.colored();
-        let text = match status {
-          CommandStatus::Failed(msg) => {
-            format!(
-              "{} {}: {} - {}ms ({})",
-              symbol, state.filename, state.command, duration, msg
-            )
+    let mut lines = Vec::with_capacity(self.states.len());
+
+    for (idx, state) in self.get_states().iter().enumerate() {
+      let status = &statuses[idx];
+      let duration = durations[idx];
+      let (symbol, mut color) = status.colored(symbols);
+      // Файл уже в заголовке, поэтому при группировке по файлам строка начинается с группы
+      let label = match group_by {
+        GroupBy::Group => &state.filename,
+        GroupBy::File => &state.group_name,
+      };
+      // Длительность рисуется отдельной колонкой, в текст не входит
+      let mut text = match status {
+        CommandStatus::Failed(msg) => {
+          format!("{} {}: {} ({})", symbol, label, state.command, msg)
+        }
+        _ => format!("{} {}: {}", symbol, label, state.command),
+      };
+
+      // Долгое молчание - повод решить, ждать дальше или прервать команду
+      if let Some(silence) = state.silent_for().await
+        && silence >= SILENCE_WARNING
+      {
+        text.push_str(&format!(" - no output for {}s", silence.as_secs()));
+        color = Color::Yellow;
+      }
+
+      // Сколько таймаута уже прошло: предупреждение до того, как задачу убьют
+      let timeout_share = match (status, state.timeout, state.timeout_clock) {
+        (CommandStatus::Running, Some(timeout), TimeoutClock::Wall) if !timeout.is_zero() => state
+          .attempt_started_at
+          .lock()
+          .await
+          .map(|started| started.elapsed().as_secs_f64() / timeout.as_secs_f64()),
+        _ => None,
+      };
+
+      lines.push((text, color, duration, timeout_share));
+    }
+
+    lines
+  }
+
+  /// Files of the tasks that were started: only their commands could rewrite them.
+  pub async fn started_files(&self) -> Vec<String> {
+    let mut files = BTreeSet::new();
+
+    for state in &self.states {
+      if state.started_at.lock().await.is_some() {
+        files.extend(state.target_files());
+      }
+    }
+
+    files.into_iter().collect()
+  }
+
+  /// Returns the `limit` slowest tasks as `(label, duration_ms)`, slowest first.
+  ///
+  /// Running tasks count with the time elapsed so far.
+  pub async fn get_slowest_tasks(&self, limit: usize) -> Vec<(String, u128)> {
+    let mut slowest = Vec::new();
+
+    for state in &self.states {
+      let duration = match state.get_status().await {
+        CommandStatus::Running => state
+          .started_at
+          .lock()
+          .await
+          .map(|started| started.elapsed().as_millis())
+          .unwrap_or(0),
+        _ => state.get_duration_ms().await,
+      };
+
+      if duration > 0 {
+        slowest.push((format!("{}: {}", state.filename, state.command), duration));
+      }
+    }
+
+    slowest.sort_by_key(|(_, duration)| std::cmp::Reverse(*duration));
+    slowest.truncate(limit);
+
+    slowest
+  }
+
+  /// Splits rendered task lines into per-group (or per-file) views, sorted by name.
+  pub fn group_views(
+    &self,
+    lines: &[TaskLine],
+    statuses: &[CommandStatus],
+    sort: TaskSort,
+    group_by: GroupBy,
+  ) -> Vec<GroupView> {
+    // Индексы задач каждой группы; сами `states` не переставляются
+    let mut by_group: HashMap<&str, Vec<usize>> = HashMap::new();
+
+    for (idx, state) in self.states.iter().enumerate() {
+      let key = match group_by {
+        GroupBy::Group => state.group_name.as_str(),
+        GroupBy::File => state.filename.as_str(),
+      };
+
+      by_group.entry(key).or_default().push(idx);
+    }
+
+    let mut groups: Vec<GroupView> = by_group
+      .into_iter()
+      .map(|(name, mut indices)| {
+        match sort {
+          TaskSort::Status => indices.sort_by_key(|&idx| status_priority(&statuses[idx])),
+          TaskSort::Duration => indices.sort_by_key(|&idx| std::cmp::Reverse(lines[idx].2)),
+          TaskSort::File => {
+            indices.sort_by(|&a, &b| self.states[a].filename.cmp(&self.states[b].filename))
           }
-          _ => format!(
-            "{} {}: {} - {}ms",
-            symbol, state.filename, state.command, duration
-          ),
+          TaskSort::None => {}
+        }
+
+        let tasks: Vec<TaskLine> = indices.iter().map(|&idx| lines[idx].clone()).collect();
+        let statuses: Vec<CommandStatus> =
+          indices.iter().map(|&idx| statuses[idx].clone()).collect();
+
+        let failed = statuses.iter().any(CommandStatus::is_failure);
+        let pending = statuses
+          .iter()
+          .any(|status| matches!(status, CommandStatus::Waiting | CommandStatus::Running));
+
+        let color = if failed {
+          Color::Red
+        } else if pending {
+          Color::Yellow
+        } else {
+          Color::Green
         };
 
-        (text, color, duration)
+        GroupView {
+          name: name.to_string(),
+          rollup: status_rollup(&statuses),
+          color,
+          tasks,
+          indices,
+        }
       })
-      .collect()
+      .collect();
+
+    groups.sort_by(|a, b| a.name.cmp(&b.name));
+
+    groups
+  }
+
+  /// Title and captured output of a task for the output pane, stderr after stdout.
+  pub async fn output_view(&self, idx: usize) -> Option<(String, String)> {
+    let state = self.states.get(idx)?;
+    let stdout = state.stdout.lock().await.text();
+    let stderr = state.stderr.lock().await.text();
+
+    let text = match (stdout.trim_end().is_empty(), stderr.trim_end().is_empty()) {
+      (_, true) => stdout,
+      (true, false) => stderr,
+      (false, false) => format!("{}\n{}", stdout.trim_end(), stderr),
+    };
+
+    Some((format!("{}: {}", state.filename, state.command), text))
   }
 
   pub async fn get_total_execution_time(&self) -> u128 {
@@ -92,7 +314,9 @@ impl TaskPool {
       .iter()
       .zip(durations.iter())
       .map(|(status, duration)| match status {
-        CommandStatus::Done | CommandStatus::Failed(_) => *duration,
+        CommandStatus::Done | CommandStatus::DoneWithWarnings | CommandStatus::Failed(_) => {
+          *duration
+        }
         _ => 0,
       })
       .sum()
@@ -122,9 +346,26 @@ impl TaskPool {
     statuses
   }
 
+  /// Totals of the current task states.
+  pub async fn summary(&self, wall_ms: u128) -> RunSummary {
+    RunSummary::from_statuses(&self.statuses().await, wall_ms)
+  }
+
+  /// Collects a structured record for every task.
+  pub async fn report(&self) -> Vec<TaskReport> {
+    let mut reports = Vec::new();
+
+    for state in &self.states {
+      reports.push(TaskReport::from_task(state).await);
+    }
+
+    reports
+  }
+
+  /// Reaps finished tasks without waiting, so the render tick keeps going while commands run.
   pub async fn pull_task(&mut self) -> Result<()> {
-    if let Some(res) = self.join_set.join_next().await {
-      return res.map_err(|err| AppError::TaskJoinError(err));
+    while let Some(res) = self.join_set.try_join_next() {
+      res.map_err(|err| AppError::TaskJoinError(err))?;
     }
 
     Ok(())
@@ -145,14 +386,31 @@ impl TaskPool {
     // Ok(all_done.iter().all(|done| *done == true))
   }
 
-  pub async fn execute_commands(&mut self, file_commands: Vec<FileCommand>) -> Result<()> {
-    // Проверяем наличие всех команд перед запуском
+  /// Starts all commands in `root`; returns the commands that were skipped as missing from PATH.
+  pub async fn execute_commands(
+    &mut self,
+    root: &Path,
+    file_commands: Vec<FileCommand>,
+    on_missing: OnMissingCommand,
+  ) -> Result<Vec<String>> {
+    // Проверяем наличие всех команд перед запуском; в группах с `when`
+    // инструмента может не быть как раз поэтому, там проверяет условие
+    let mut missing: Vec<String> = Vec::new();
+
     for file_cmd in &file_commands {
-      if !&file_cmd.command_exists() {
-        return Err(AppError::CommandNotFound {
-          command: file_cmd.command.clone(),
-          reason: "Command not found in PATH".to_string(),
-        });
+      if file_cmd.when.is_some() || missing.contains(&file_cmd.command) {
+        continue;
+      }
+
+      if !file_cmd.command_exists(root) {
+        if on_missing == OnMissingCommand::Error {
+          return Err(AppError::CommandNotFound {
+            command: file_cmd.command.clone(),
+            reason: "Command not found in PATH".to_string(),
+          });
+        }
+
+        missing.push(file_cmd.command.clone());
       }
     }
 
@@ -165,78 +423,501 @@ impl TaskPool {
         .push(cmd);
     }
 
+    // Общие блокировки для групп с одинаковой меткой `mutex`
+    let mut locks: HashMap<String, Arc<Mutex<()>>> = HashMap::new();
+    // Запуски групп по приоритету, в порядке возрастания
+    let mut buckets: BTreeMap<i32, Vec<GroupRun>> = BTreeMap::new();
+
     for (_, group_cmds) in by_group {
       if group_cmds.is_empty() {
         continue;
       }
 
       let order = group_cmds[0].execution_order;
+      let priority = group_cmds[0].priority;
+      let stop_on_error = group_cmds[0].stop_on_error;
+      let group_fail_fast = group_cmds[0].group_fail_fast;
+      let when = group_cmds[0].when.clone();
+      let env = group_cmds[0].env.clone();
+      let clean_env = group_cmds[0].clean_env.clone();
+      let lock = group_cmds[0]
+        .mutex
+        .as_ref()
+        .map(|label| locks.entry(label.clone()).or_default().clone());
+
+      // Независимые задачи и цепочки шагов (`chain`), каждая цепочка - один блок
+      let mut units: Vec<Vec<Task>> = Vec::new();
+      let mut chain_units: HashMap<String, usize> = HashMap::new();
+
+      for file_cmd in &group_cmds {
+        let state = Task::from_file_command(file_cmd.clone(), root);
+
+        self.add(state.clone());
+
+        // Закешированные задачи показываем, но не запускаем
+        if file_cmd.cached {
+          continue;
+        }
+
+        // Задачи без установленного инструмента сразу пропускаются
+        if missing.contains(&file_cmd.command) {
+          skip(vec![state]).await;
+          continue;
+        }
 
-      match order {
-        ExecutionOrder::Parallel => {
-          // Параллельный запуск с использованием JoinSet для управления задачами
+        match &file_cmd.chain {
+          Some(chain) if let Some(&idx) = chain_units.get(chain) => units[idx].push(state),
+          Some(chain) => {
+            chain_units.insert(chain.clone(), units.len());
+            units.push(vec![state]);
+          }
+          None => units.push(vec![state]),
+        }
+      }
 
-          for file_cmd in group_cmds {
-            let state = Task::from_file_command(file_cmd.clone());
+      if units.is_empty() {
+        continue;
+      }
 
-            self.add(state.clone());
+      // Задачи, которые пропускаются, если условие `when` не выполнилось
+      let gated: Vec<Task> = units.iter().flatten().cloned().collect();
+
+      let run: GroupRun = match (order, lock) {
+        (ExecutionOrder::Parallel, lock) => {
+          // Параллельный запуск внутри группы, с блокировкой - только после ее захвата
+          Box::pin(async move {
+            let _guard = match &lock {
+              Some(lock) => Some(lock.lock().await),
+              None => None,
+            };
+            let mut group_set = JoinSet::new();
+            let mut handles = Vec::new();
+
+            for steps in units {
+              let tasks = steps.clone();
+              let handle = group_set.spawn(run_steps(steps));
+              handles.push((handle, tasks));
+            }
 
-            self.join_set.spawn(async move {
-              state
-                .clone()
-                .run_single_command(file_cmd.clone().timeout)
-                .await;
-            });
-          }
+            while let Some(result) = group_set.join_next().await {
+              if group_fail_fast && matches!(result, Ok(true)) {
+                // Сброс `Process` убивает только `sh`, поэтому до прерывания
+                // сигнал получает вся группа процессов, как при таймауте
+                let mut running = Vec::new();
+
+                for task in handles.iter().flat_map(|(_, tasks)| tasks) {
+                  if let Some(pid) = *task.pid.lock().await {
+                    signal_process_group(pid, Signal::Term);
+                    running.push((pid, task.kill_grace));
+                  }
+                }
+
+                for (handle, _) in &handles {
+                  handle.abort();
+                }
+
+                while group_set.join_next().await.is_some() {}
+
+                for (_, tasks) in handles {
+                  cancel_unfinished(tasks).await;
+                }
+
+                kill_after_grace(running).await;
+
+                break;
+              }
+            }
+          })
         }
-        ExecutionOrder::Sequential => {
+        (ExecutionOrder::Sequential, lock) => {
           // Последовательный запуск: одна задача на группу
-          let group_states: Vec<_> = group_cmds
-            .iter()
-            .map(|file_cmd| {
-              let state = Task::from_file_command(file_cmd.clone());
+          Box::pin(async move {
+            let _guard = match &lock {
+              Some(lock) => Some(lock.lock().await),
+              None => None,
+            };
 
-              self.add(state.clone());
+            let mut units = units.into_iter();
 
-              (state.clone(), file_cmd.timeout.clone())
-            })
-            .collect();
+            while let Some(steps) = units.next() {
+              let failed = run_steps(steps).await;
 
-          self.join_set.spawn(async move {
-            for (state, timeout_str) in group_states {
-              state.run_single_command(timeout_str).await;
+              if stop_on_error && failed {
+                cancel(units.by_ref().flatten()).await;
+              }
             }
-          });
+          })
         }
+      };
+
+      let root = root.to_path_buf();
+      let run: GroupRun = match when {
+        Some(when) => Box::pin(async move {
+          if condition_holds(&when, &env, clean_env.as_deref(), &root).await {
+            run.await;
+          } else {
+            skip(gated).await;
+          }
+        }),
+        None => run,
+      };
+
+      buckets.entry(priority).or_default().push(run);
+    }
+
+    let changed = self.changed.clone();
+
+    if buckets.len() <= 1 {
+      for run in buckets.into_values().flatten() {
+        let changed = changed.clone();
+
+        // Будим UI еще раз, когда группа целиком завершилась и её можно собрать
+        self.join_set.spawn(async move {
+          run.await;
+          changed.notify_one();
+        });
       }
+    } else {
+      // Следующий приоритет стартует только после завершения всех групп предыдущего
+      self.join_set.spawn(async move {
+        for runs in buckets.into_values() {
+          let mut bucket_set = JoinSet::new();
+
+          for run in runs {
+            bucket_set.spawn(run);
+          }
+
+          while bucket_set.join_next().await.is_some() {}
+        }
+
+        changed.notify_one();
+      });
     }
 
-    Ok(())
+    Ok(missing)
+  }
+}
+
+/// Position of a status in the `status` task order: what needs attention comes first.
+fn status_priority(status: &CommandStatus) -> u8 {
+  match status {
+    CommandStatus::Failed(_) => 0,
+    CommandStatus::Timeout => 1,
+    CommandStatus::Running => 2,
+    CommandStatus::DoneWithWarnings => 3,
+    CommandStatus::Waiting | CommandStatus::None => 4,
+    CommandStatus::Cancelled => 5,
+    CommandStatus::Done => 6,
+    CommandStatus::Skipped => 7,
+  }
+}
+
+/// Runs the steps of a chain one after another; a single task is a chain of one.
+///
+/// Returns whether a step failed, in which case the remaining steps are cancelled
+/// unless the failed step has `continue_on_error`.
+async fn run_steps(steps: Vec<Task>) -> bool {
+  let mut steps = steps.into_iter();
+
+  while let Some(state) = steps.next() {
+    state.run_single_command().await;
+
+    let failed = state.get_status().await.is_failure();
+
+    if failed && !state.continue_on_error {
+      cancel(steps).await;
+      return true;
+    }
+  }
+
+  false
+}
+
+/// Runs a group's `when` command in `root` with the environment its commands get;
+/// the group runs only if it exits successfully.
+async fn condition_holds(
+  command: &str,
+  env: &HashMap<String, String>,
+  clean_env: Option<&[String]>,
+  root: &Path,
+) -> bool {
+  let mut condition = Command::new("sh");
+  condition.arg("-c").arg(command).current_dir(root);
+  set_env(&mut condition, env, clean_env);
+
+  condition
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .kill_on_drop(true)
+    .status()
+    .await
+    .is_ok_and(|status| status.success())
+}
+
+/// Sets the environment of a group's command: the group's `env` over the inherited one.
+fn set_env(command: &mut Command, env: &HashMap<String, String>, clean_env: Option<&[String]>) {
+  // С `clean_env` от окружения остаются только PATH и разрешенные переменные
+  if let Some(allowed) = clean_env {
+    command.env_clear().envs(
+      std::env::vars_os()
+        .filter(|(key, _)| key == "PATH" || allowed.iter().any(|name| key == name.as_str())),
+    );
+  }
+
+  command.envs(env).env(RUNNING_ENV, "1");
+}
+
+async fn skip(tasks: Vec<Task>) {
+  for state in tasks {
+    state.set_status(CommandStatus::Skipped).await;
+    state.set_done().await;
+  }
+}
+
+/// Marks tasks that were aborted before reaching a final state as cancelled.
+async fn cancel_unfinished(tasks: Vec<Task>) {
+  for state in tasks {
+    if !state.get_status().await.is_finished() {
+      state.set_status(CommandStatus::Cancelled).await;
+      state.set_done().await;
+    }
+  }
+}
+
+async fn cancel(steps: impl Iterator<Item = Task>) {
+  for state in steps {
+    state.set_status(CommandStatus::Cancelled).await;
+    state.set_done().await;
   }
 }
 
 #[derive(Clone, Debug)]
 pub struct Task {
+  // Корень репозитория: каталог запуска, от него считаются пути файлов
+  pub root: PathBuf,
   pub filename: String,
+  // Файлы команды на всю группу (`scope = "group"`), иначе пусто
+  pub files: Arc<Vec<String>>,
   pub command: String,
-  // pub group_name: Option<String>,
+  pub argv: Option<Vec<String>>,
+  pub group_name: String,
+  pub line_ranges: Option<Vec<LineRange>>,
+  pub kill_grace: Duration,
+  pub env: HashMap<String, String>,
+  pub warning_pattern: Option<Regex>,
+  pub clean_env: Option<Vec<String>>,
+  pub fail_on_output: Option<Regex>,
+  pub filter_output: Vec<Regex>,
+  pub continue_on_error: bool,
+  pub stdin: StdinMode,
+  pub success_codes: Vec<i32>,
+  pub tty: bool,
+  pub absolute_paths: bool,
+  pub retry_on: RetryOn,
+  pub retries: usize,
+  pub timeout: Option<Duration>,
+  pub timeout_clock: TimeoutClock,
+  pub fail_on_changes: bool,
+  pub nice: Option<i32>,
+  pub command_prefix: Vec<String>,
+  // Хвосты stdout и stderr, заполняются по мере выполнения
+  pub stdout: Arc<Mutex<OutputTail>>,
+  pub stderr: Arc<Mutex<OutputTail>>,
   pub status: Arc<Mutex<CommandStatus>>,
   pub started_at: Arc<Mutex<Option<Instant>>>,
+  // Начало текущей попытки: таймаут с каждым повтором отсчитывается заново
+  pub attempt_started_at: Arc<Mutex<Option<Instant>>>,
+  pub started_wall: Arc<Mutex<Option<SystemTime>>>,
+  pub finished_wall: Arc<Mutex<Option<SystemTime>>>,
   pub duration_ms: Arc<Mutex<Option<u128>>>,
+  // Время CPU и пиковая память, известны после завершения (только unix)
+  pub usage: Arc<Mutex<Option<ResourceUsage>>>,
+  // Почему команда не запустилась: отличает сломанную команду от упавшей проверки
+  pub spawn_error: Arc<Mutex<Option<std::io::ErrorKind>>>,
+  // Группа процессов идущей попытки, чтобы ее можно было остановить снаружи
+  pub pid: Arc<Mutex<Option<u32>>>,
   pub done: Arc<Mutex<bool>>,
+  pub changed: Arc<Notify>,
 }
 
 impl Task {
-  pub fn from_file_command(file_cmd: FileCommand) -> Self {
+  pub fn from_file_command(file_cmd: FileCommand, root: &Path) -> Self {
+    let output_limit = file_cmd
+      .output_limit_kb
+      .map_or(DEFAULT_OUTPUT_LIMIT, |kb| kb * 1024);
+
     Task {
+      root: root.to_path_buf(),
       filename: file_cmd.filename.clone(),
+      files: file_cmd.files.clone(),
       command: file_cmd.command.clone(),
-      // group_name: Some(file_cmd.group_name.clone()),
-      status: Arc::new(Mutex::new(CommandStatus::Waiting)),
+      argv: file_cmd.argv.clone(),
+      group_name: file_cmd.group_name.clone(),
+      line_ranges: file_cmd.line_ranges.clone(),
+      kill_grace: file_cmd
+        .kill_grace
+        .as_deref()
+        .and_then(|s| parse_duration::parse(s).ok())
+        .unwrap_or(DEFAULT_KILL_GRACE),
+      env: file_cmd.env.clone(),
+      warning_pattern: file_cmd.warning_pattern.clone(),
+      clean_env: file_cmd.clean_env.clone(),
+      fail_on_output: file_cmd.fail_on_output.clone(),
+      filter_output: file_cmd.filter_output.clone(),
+      continue_on_error: file_cmd.continue_on_error,
+      stdin: file_cmd.stdin,
+      success_codes: file_cmd.success_codes.clone(),
+      tty: file_cmd.tty,
+      absolute_paths: file_cmd.absolute_paths,
+      retry_on: file_cmd.retry_on,
+      retries: file_cmd.retries,
+      timeout: file_cmd
+        .timeout
+        .as_deref()
+        .and_then(|timeout| parse_duration::parse(timeout).ok()),
+      timeout_clock: file_cmd.timeout_clock,
+      fail_on_changes: file_cmd.fail_on_changes,
+      nice: file_cmd.nice,
+      command_prefix: file_cmd.command_prefix.clone(),
+      stdout: Arc::new(Mutex::new(OutputTail::new(output_limit))),
+      stderr: Arc::new(Mutex::new(OutputTail::new(output_limit))),
+      status: Arc::new(Mutex::new(if file_cmd.cached {
+        CommandStatus::Skipped
+      } else {
+        CommandStatus::Waiting
+      })),
       started_at: Arc::new(Mutex::new(None)),
+      attempt_started_at: Arc::new(Mutex::new(None)),
+      started_wall: Arc::new(Mutex::new(None)),
+      finished_wall: Arc::new(Mutex::new(None)),
       duration_ms: Arc::new(Mutex::new(None)),
-      done: Arc::new(Mutex::new(false)),
+      usage: Arc::new(Mutex::new(None)),
+      spawn_error: Arc::new(Mutex::new(None)),
+      pid: Arc::new(Mutex::new(None)),
+      done: Arc::new(Mutex::new(file_cmd.cached)),
+      changed: Arc::new(Notify::new()),
+    }
+  }
+
+  /// Builds the shell command line, substituting `{file}`, `{files}` and `{ranges}` placeholders.
+  pub fn shell_command(&self) -> String {
+    let files: Vec<String> = self
+      .command_paths()
+      .iter()
+      .map(|path| shell_quote(path))
+      .collect();
+    let mut command = self
+      .command
+      .replace("{files}", &files.join(" "))
+      .replace("{file}", &shell_quote(&self.file_arg()));
+
+    if let Some(ranges) = &self.line_ranges {
+      let ranges: Vec<String> = ranges.iter().map(|range| range.to_string()).collect();
+      command = command.replace("{ranges}", &ranges.join(" "));
     }
+
+    command
+  }
+
+  /// Builds argv for shell-less commands, substituting placeholders without quoting.
+  ///
+  /// A standalone `{ranges}` or `{files}` argument expands to one argument per item.
+  pub fn argv_command(&self) -> Option<Vec<String>> {
+    let ranges: Vec<String> = self
+      .line_ranges
+      .iter()
+      .flatten()
+      .map(|range| range.to_string())
+      .collect();
+
+    let argv = self.argv.as_ref()?;
+    let mut args = Vec::with_capacity(argv.len());
+
+    let files = self.command_paths();
+
+    for arg in argv {
+      if arg == "{ranges}" {
+        args.extend(ranges.iter().cloned());
+      } else if arg == "{files}" {
+        args.extend(files.iter().cloned());
+      } else {
+        args.push(
+          arg
+            .replace("{files}", &files.join(" "))
+            .replace("{file}", &self.file_arg())
+            .replace("{ranges}", &ranges.join(" ")),
+        );
+      }
+    }
+
+    Some(args)
+  }
+
+  /// Repo-relative paths of the files this task runs for.
+  fn target_files(&self) -> Vec<String> {
+    if self.files.is_empty() {
+      vec![self.filename.clone()]
+    } else {
+      self.files.to_vec()
+    }
+  }
+
+  /// Program to spawn; a relative path like `./bin/lint` is taken from the repository root.
+  fn program_path(&self, program: String) -> PathBuf {
+    let path = Path::new(&program);
+
+    if path.is_relative() && path.components().count() > 1 {
+      self.root.join(path)
+    } else {
+      PathBuf::from(program)
+    }
+  }
+
+  /// Whether the exit status counts as success: exit code 0, or one of `success_codes`.
+  fn is_success(&self, status: std::process::ExitStatus) -> bool {
+    status.success()
+      || status
+        .code()
+        .is_some_and(|code| self.success_codes.contains(&code))
+  }
+
+  /// File piped to stdin with `stdin = "file"`; group-scope and tty commands have none.
+  fn stdin_file(&self) -> Option<&str> {
+    (self.stdin == StdinMode::File && self.files.is_empty() && !self.tty)
+      .then_some(self.filename.as_str())
+  }
+
+  /// Value for `{file}`: the staged path, or the label of a group-scope command.
+  fn file_arg(&self) -> String {
+    if self.files.is_empty() {
+      self.command_path(&self.filename)
+    } else {
+      self.filename.clone()
+    }
+  }
+
+  /// Files for `{files}`: all files of a group-scope command, otherwise the task's file.
+  fn command_paths(&self) -> Vec<String> {
+    if self.files.is_empty() {
+      return vec![self.command_path(&self.filename)];
+    }
+
+    self
+      .files
+      .iter()
+      .map(|file| self.command_path(file))
+      .collect()
+  }
+
+  /// A staged path as passed to the command, absolute with `absolute_paths`.
+  fn command_path(&self, file: &str) -> String {
+    if !self.absolute_paths {
+      return file.to_string();
+    }
+
+    // Команды запускаются из корня репозитория, пути индекса считаются от него
+    self.root.join(file).to_string_lossy().into_owned()
   }
 
   pub async fn get_done(&self) -> bool {
@@ -247,12 +928,22 @@ impl Task {
     self.status.lock().await.clone()
   }
 
+  pub async fn get_usage(&self) -> Option<ResourceUsage> {
+    *self.usage.lock().await
+  }
+
+  /// Why the command could not be started, if that is why it failed.
+  pub async fn get_spawn_error(&self) -> Option<std::io::ErrorKind> {
+    *self.spawn_error.lock().await
+  }
+
   pub async fn get_duration_ms(&self) -> u128 {
     self.duration_ms.lock().await.clone().unwrap_or(0)
   }
 
   pub async fn set_status(&self, status: CommandStatus) {
     *self.status.lock().await = status;
+    self.changed.notify_one();
   }
 
   pub async fn set_duration_ms(&self, duration_ms: u128) {
@@ -263,77 +954,554 @@ impl Task {
     *self.started_at.lock().await = started_at;
   }
 
-  pub async fn set_done(&self) {
-    *self.done.lock().await = true;
+  /// Time since a running task last printed anything, or since it started if it never did.
+  pub async fn silent_for(&self) -> Option<Duration> {
+    if self.get_status().await != CommandStatus::Running {
+      return None;
+    }
+
+    let started = (*self.started_at.lock().await)?;
+    let last_output = [
+      self.stdout.lock().await.last_output(),
+      self.stderr.lock().await.last_output(),
+    ]
+    .into_iter()
+    .flatten()
+    .fold(started, Instant::max);
+
+    Some(last_output.elapsed())
   }
 
-  pub async fn parse_timeout(&self, timeout_str: Option<String>) -> Option<Duration> {
-    timeout_str
-      .as_deref()
-      .and_then(|s| parse_duration::parse(s).ok())
+  pub async fn get_started_wall(&self) -> Option<SystemTime> {
+    *self.started_wall.lock().await
   }
 
-  pub async fn run_single_command(&self, timeout_str: Option<String>) {
+  pub async fn get_finished_wall(&self) -> Option<SystemTime> {
+    *self.finished_wall.lock().await
+  }
+
+  pub async fn set_done(&self) {
+    *self.done.lock().await = true;
+    self.changed.notify_one();
+  }
+
+  pub async fn run_single_command(&self) {
     // Обновляем статус на Running
     let started = Instant::now();
 
     self.set_status(CommandStatus::Running).await;
     self.set_started_at(Some(started)).await;
+    *self.started_wall.lock().await = Some(SystemTime::now());
+
+    // Хеши файлов до запуска, чтобы поймать правки от команды-проверки
+    let before = if self.fail_on_changes {
+      FileSnapshot::capture(&self.root, &self.target_files())
+        .await
+        .ok()
+    } else {
+      None
+    };
+
+    // Повтор только для тех исходов, что разрешены `retry_on`
+    let mut status = self.run_attempt(self.timeout).await;
+    let mut attempts = 0;
+
+    while attempts < self.retries && self.retry_on.matches(&status) {
+      attempts += 1;
+      // В выводе остается только последняя попытка
+      self.stdout.lock().await.clear();
+      self.stderr.lock().await.clear();
+      status = self.run_attempt(self.timeout).await;
+    }
 
-    // let timeout_dur = self.parse_timeout(timeout_str).await;
+    if let Some(before) = before
+      && matches!(
+        status,
+        CommandStatus::Done | CommandStatus::DoneWithWarnings
+      )
+      && let Ok(modified) = before.modified_files(&self.target_files()).await
+      && !modified.is_empty()
+    {
+      status = CommandStatus::Failed(format!(
+        "files would be reformatted: {}",
+        modified.join(", ")
+      ));
+    }
+
+    self.set_status(status).await;
+    self.set_duration_ms(started.elapsed().as_millis()).await;
+    *self.finished_wall.lock().await = Some(SystemTime::now());
+    self.set_done().await;
+  }
+
+  /// Spawns the command once and waits for it, killing it on timeout.
+  async fn run_attempt(&self, timeout_dur: Option<Duration>) -> CommandStatus {
+    *self.spawn_error.lock().await = None;
+    *self.attempt_started_at.lock().await = Some(Instant::now());
+
+    // Команда-список запускается напрямую, строка - через `sh -c`
+    // Обертка ставится перед программой, а у строки - перед текстом для `sh -c`
+    let mut command = match self.argv_command() {
+      Some(argv) => {
+        let mut args = self.command_prefix.iter().cloned().chain(argv);
+        let mut command = Command::new(self.program_path(args.next().unwrap_or_default()));
+        command.args(args);
+        command
+      }
+      None => {
+        let mut line: Vec<String> = self
+          .command_prefix
+          .iter()
+          .map(|arg| shell_quote(arg))
+          .collect();
+        line.push(self.shell_command());
+
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(line.join(" "));
+        command
+      }
+    };
+
+    command.current_dir(&self.root);
+    set_env(&mut command, &self.env, self.clean_env.as_deref());
+
+    // Отдельная группа процессов, чтобы сигнал дошел и до потомков `sh`
+    #[cfg(unix)]
+    command.process_group(0);
+
+    if let Some(nice) = self.nice.filter(|nice| *nice > 0) {
+      lower_priority(&mut command, nice);
+    }
+
+    // Содержимое читаем до запуска: stdout команды потом заменит файл
+    let input = match self.stdin_file() {
+      Some(file) => match tokio::fs::read(self.root.join(file)).await {
+        Ok(content) => Some(content),
+        Err(err) => return CommandStatus::Failed(format!("cannot read {}: {}", file, err)),
+      },
+      None => None,
+    };
+
+    let program = command
+      .as_std()
+      .get_program()
+      .to_string_lossy()
+      .into_owned();
+    let spawned = self
+      .connect_stdio(&mut command)
+      .and_then(|pty| Ok((Process::spawn(&mut command)?, pty)));
+    // `Command` держит копии дескрипторов терминала, без них чтение не увидит конец вывода
+    drop(command);
+
+    match spawned {
+      Ok((mut process, pty)) => {
+        let pid = process.id();
+        *self.pid.lock().await = pid;
+        let stdin = process.stdin.take();
+        let stdout = process.stdout.take();
+        let stderr = process.stderr.take();
+        let terminal = pty.map(Pty::into_reader);
+        // С `stdin = "file"` stdout - новое содержимое файла, он нужен целиком
+        let (result_stdout, stdout) = match input {
+          Some(_) => (stdout, None),
+          None => (None, stdout),
+        };
+
+        let feed = async {
+          if let (Some(mut stdin), Some(input)) = (stdin, input.as_ref()) {
+            // Команда может не дочитать вход, это не ошибка запуска
+            let _ = stdin.write_all(input).await;
+          }
+        };
 
-    // Запускаем команду
-    // let command_future = Command::new("sh").arg("-c").arg(&self.command).output();
+        // Вывод читается потоково в ограниченный буфер, а не копится целиком
+        let output = async {
+          let (status, _, _, _, _, result) = tokio::join!(
+            process.wait(),
+            feed,
+            capture(stdout, &self.stdout),
+            capture(stderr, &self.stderr),
+            capture(terminal, &self.stdout),
+            read_all(result_stdout)
+          );
+          status.map(|status| (status, result))
+        };
+        tokio::pin!(output);
+
+        let result = match (timeout_dur, self.timeout_clock) {
+          (Some(dur), TimeoutClock::Wall) => match timeout(dur, output.as_mut()).await {
+            Ok(result) => Some(result),
+            Err(_) => {
+              self.terminate(pid, output.as_mut()).await;
+              None
+            }
+          },
+          (Some(dur), TimeoutClock::Cpu) => {
+            let finished = tokio::select! {
+              result = output.as_mut() => Some(result),
+              _ = cpu_time_exceeded(pid, dur) => None,
+            };
+
+            if finished.is_none() {
+              self.terminate(pid, output.as_mut()).await;
+            }
 
-    let command_future = Command::new(&self.command).output();
+            finished
+          }
+          (None, _) => Some(output.await),
+        };
 
-    // if let Some(dur) = timeout_dur {
-    //   println!("timeout {:?}", dur);
+        *self.pid.lock().await = None;
 
-    //   let status = match timeout(dur, command_future).await {
-    //     Ok(output) if output.is_ok() => CommandStatus::Done,
-    //     Ok(output) if output.is_err() => {
-    //       let message = output.unwrap_err().to_string();
-    //       println!("timeout output error {:?}", message);
-    //       CommandStatus::Failed(message)
-    //     }
-    //     Ok(_) => CommandStatus::Failed("timeout".to_string()),
-    //     Err(err) => {
-    //       println!("timeout error {:?}", err);
+        // Шум убирается до проверок по выводу, показа и отчета
+        if !self.filter_output.is_empty() {
+          let noise = |line: &str| self.filter_output.iter().any(|regex| regex.is_match(line));
+          self.stdout.lock().await.remove_lines(noise);
+          self.stderr.lock().await.remove_lines(noise);
+        }
 
-    //       CommandStatus::Timeout
-    //     }
-    //   };
+        match result {
+          None => CommandStatus::Timeout,
+          Some(Ok(((status, usage), result))) if self.is_success(status) => {
+            *self.usage.lock().await = usage;
 
-    //   println!("Running command status: {:?}", status);
+            if let (Some(file), Some(input)) = (self.stdin_file(), input.as_ref())
+              && result != *input
+              && let Err(err) = tokio::fs::write(self.root.join(file), &result).await
+            {
+              return CommandStatus::Failed(format!("cannot write {}: {}", file, err));
+            }
 
-    //   self.set_status(status).await;
-    // } else {
-    //   let status = match command_future.await {
-    //     Ok(_) => CommandStatus::Done,
-    //     Err(err) => {
-    //       let message = err.to_string();
+            let stdout = self.stdout.lock().await.text();
 
-    //       CommandStatus::Failed(message)
-    //     }
-    //   };
+            if let Some(pattern) = &self.fail_on_output
+              && (pattern.is_match(&stdout) || pattern.is_match(&self.stderr.lock().await.text()))
+            {
+              return CommandStatus::Failed("output matched fail_on_output".to_string());
+            }
 
-    //   println!("Running command status: {:?}", status);
+            let has_warnings = self
+              .warning_pattern
+              .as_ref()
+              .is_some_and(|pattern| pattern.is_match(&stdout));
 
-    //   self.set_status(status).await;
-    // };
+            if has_warnings {
+              CommandStatus::DoneWithWarnings
+            } else {
+              CommandStatus::Done
+            }
+          }
+          // Через `sh -c` ненулевой код выхода - единственный признак ошибки
+          Some(Ok(((status, usage), result))) => {
+            *self.usage.lock().await = usage;
+            // При ошибке в stdout обычно сообщение, а не содержимое файла
+            self.stdout.lock().await.push(&result);
+
+            // `sh` сообщает о незапустившейся команде кодами 127 и 126
+            let shell_spawn_error = match status.code() {
+              Some(127) if self.argv.is_none() => Some(std::io::ErrorKind::NotFound),
+              Some(126) if self.argv.is_none() => Some(std::io::ErrorKind::PermissionDenied),
+              _ => None,
+            };
+
+            if let Some(kind) = shell_spawn_error {
+              *self.spawn_error.lock().await = Some(kind);
+            }
 
-    let status = match command_future.await {
-      Ok(_) => CommandStatus::Done,
+            CommandStatus::Failed(match (status.code(), shell_spawn_error) {
+              (Some(code), Some(kind)) => {
+                format!("exit code {} ({})", code, spawn_error_reason(kind))
+              }
+              (Some(code), None) => format!("exit code {}", code),
+              (None, _) => "terminated by signal".to_string(),
+            })
+          }
+          Some(Err(err)) => CommandStatus::Failed(err.to_string()),
+        }
+      }
       Err(err) => {
-        let message = err.to_string();
+        *self.spawn_error.lock().await = Some(err.kind());
+
+        let reason = match err.kind() {
+          std::io::ErrorKind::NotFound | std::io::ErrorKind::PermissionDenied => {
+            spawn_error_reason(err.kind())
+          }
+          _ => err.to_string(),
+        };
 
-        CommandStatus::Failed(message)
+        CommandStatus::Failed(format!("cannot start {}: {}", program, reason))
       }
+    }
+  }
+
+  /// Connects the standard streams: pipes, or a pseudo-terminal for `tty = true`.
+  fn connect_stdio(&self, command: &mut Command) -> std::io::Result<Option<Pty>> {
+    if !self.tty {
+      let stdin = match self.stdin_file() {
+        Some(_) => Stdio::piped(),
+        None => Stdio::null(),
+      };
+
+      command
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+      return Ok(None);
+    }
+
+    let pty = Pty::open()?;
+    command
+      .stdin(pty.stdio()?)
+      .stdout(pty.stdio()?)
+      .stderr(pty.stdio()?);
+
+    Ok(Some(pty))
+  }
+
+  /// Stops a timed out command: SIGTERM first, SIGKILL if it is still alive after `kill_grace`.
+  async fn terminate<F: Future>(&self, pid: Option<u32>, output: Pin<&mut F>) {
+    let Some(pid) = pid else {
+      return;
     };
 
-    self.set_status(status).await;
-    self.set_duration_ms(started.elapsed().as_millis()).await;
-    self.set_done().await;
+    signal_process_group(pid, Signal::Term);
+
+    if timeout(self.kill_grace, output).await.is_err() {
+      signal_process_group(pid, Signal::Kill);
+    }
+  }
+}
+
+/// Describes why a command could not be started.
+pub fn spawn_error_reason(kind: std::io::ErrorKind) -> String {
+  match kind {
+    std::io::ErrorKind::NotFound => "command not found".to_string(),
+    std::io::ErrorKind::PermissionDenied => "permission denied".to_string(),
+    kind => kind.to_string(),
+  }
+}
+
+/// How often the CPU time of a command is checked against its timeout.
+const CPU_SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Resolves once the process group of `pid` used up `budget` of CPU time.
+///
+/// Where CPU time cannot be read, wall time since the start is used instead.
+async fn cpu_time_exceeded(pid: Option<u32>, budget: Duration) {
+  let started = Instant::now();
+
+  loop {
+    tokio::time::sleep(CPU_SAMPLE_INTERVAL).await;
+
+    let used = pid
+      .and_then(process_group_cpu_time)
+      .unwrap_or_else(|| started.elapsed());
+
+    if used >= budget {
+      return;
+    }
+  }
+}
+
+#[cfg(unix)]
+fn lower_priority(command: &mut Command, nice: i32) {
+  // Выполняется в дочернем процессе до exec, потомки наследуют приоритет
+  unsafe {
+    command.pre_exec(move || {
+      // Ошибка не критична: команда просто идет с обычным приоритетом
+      libc::nice(nice);
+      Ok(())
+    });
+  }
+}
+
+#[cfg(windows)]
+fn lower_priority(command: &mut Command, _nice: i32) {
+  const BELOW_NORMAL_PRIORITY_CLASS: u32 = 0x0000_4000;
+
+  command.creation_flags(BELOW_NORMAL_PRIORITY_CLASS);
+}
+
+#[cfg(not(any(unix, windows)))]
+fn lower_priority(_command: &mut Command, _nice: i32) {}
+
+enum Signal {
+  Term,
+  Kill,
+}
+
+#[cfg(unix)]
+fn signal_process_group(pid: u32, signal: Signal) {
+  let signal = match signal {
+    Signal::Term => libc::SIGTERM,
+    Signal::Kill => libc::SIGKILL,
+  };
+
+  // Отрицательный pid адресует всю группу процессов
+  unsafe {
+    libc::kill(-(pid as libc::pid_t), signal);
+  }
+}
+
+#[cfg(not(unix))]
+fn signal_process_group(_pid: u32, _signal: Signal) {
+  // Без сигналов процесс завершается при сбросе `Process`
+}
+
+#[cfg(unix)]
+fn process_group_alive(pid: u32) -> bool {
+  // Нулевой сигнал только проверяет, есть ли кому его доставить
+  unsafe { libc::kill(-(pid as libc::pid_t), 0) == 0 }
+}
+
+#[cfg(not(unix))]
+fn process_group_alive(_pid: u32) -> bool {
+  false
+}
+
+/// How often process groups that were sent SIGTERM are checked for exit.
+const GROUP_EXIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Sends SIGKILL to every process group in `groups` still alive after its grace period.
+async fn kill_after_grace(mut groups: Vec<(u32, Duration)>) {
+  let started = Instant::now();
+
+  while !groups.is_empty() {
+    groups.retain(|&(pid, grace)| {
+      if !process_group_alive(pid) {
+        return false;
+      }
+
+      if started.elapsed() >= grace {
+        signal_process_group(pid, Signal::Kill);
+        return false;
+      }
+
+      true
+    });
+
+    if !groups.is_empty() {
+      tokio::time::sleep(GROUP_EXIT_POLL_INTERVAL).await;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::{Config, Mode};
+  use std::path::Path;
+
+  fn tasks(config: &str, files: &[&str]) -> Vec<Task> {
+    let config = Config::from_content(Path::new(".fast-staged.toml"), config, None).unwrap();
+    let files: Vec<String> = files.iter().map(|file| file.to_string()).collect();
+
+    FileCommand::match_files_to_commands(&config, &files, Mode::Fix)
+      .unwrap()
+      .file_commands
+      .into_iter()
+      .map(|file_cmd| Task::from_file_command(file_cmd, Path::new(".")))
+      .collect()
+  }
+
+  fn pool(tasks: Vec<Task>) -> TaskPool {
+    let mut pool = TaskPool::new();
+
+    for task in tasks {
+      pool.add(task);
+    }
+
+    pool
+  }
+
+  #[test]
+  fn counts_tasks_and_distinct_files() {
+    let pool = pool(tasks(
+      r#"
+      [lint.patterns]
+      "*.js" = ["eslint", "prettier --check"]
+      "#,
+      &["a.js", "b.js"],
+    ));
+
+    assert_eq!(pool.task_count(), 4);
+    assert_eq!(pool.file_count(), 2);
+  }
+
+  #[test]
+  fn counts_files_of_group_scope_commands() {
+    let pool = pool(tasks(
+      r#"
+      [lint]
+      scope = "group"
+
+      [lint.patterns]
+      "*.js" = ["eslint", "prettier --check"]
+      "#,
+      &["a.js", "b.js", "c.js"],
+    ));
+
+    assert_eq!(pool.task_count(), 2);
+    assert_eq!(pool.file_count(), 3);
+  }
+
+  #[test]
+  fn same_command_in_another_group_is_a_separate_task() {
+    let mut pool = TaskPool::new();
+    let task = tasks(
+      r#"
+      [lint.patterns]
+      "*.js" = ["eslint"]
+      "#,
+      &["a.js"],
+    )
+    .remove(0);
+    let mut other = task.clone();
+    other.group_name = "other".to_string();
+
+    pool.add(task);
+    pool.add(other);
+
+    assert_eq!(pool.task_count(), 2);
+    assert_eq!(pool.file_count(), 1);
+  }
+
+  #[test]
+  #[cfg_attr(not(debug_assertions), ignore)]
+  #[should_panic(expected = "duplicate task for a.js: eslint")]
+  fn rejects_duplicate_tasks() {
+    let task = tasks(
+      r#"
+      [lint.patterns]
+      "*.js" = ["eslint"]
+      "#,
+      &["a.js"],
+    )
+    .remove(0);
+
+    pool(vec![task.clone(), task]);
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn success_codes_are_added_to_zero() {
+    use std::os::unix::process::ExitStatusExt;
+
+    let task = tasks(
+      r#"
+      [lint.patterns]
+      "*.js" = [{ run = "eslint", success_codes = [1] }]
+      "#,
+      &["a.js"],
+    )
+    .remove(0);
+
+    // Код выхода хранится в старшем байте статуса
+    let exit = |code: i32| std::process::ExitStatus::from_raw(code << 8);
+
+    assert!(task.is_success(exit(0)));
+    assert!(task.is_success(exit(1)));
+    assert!(!task.is_success(exit(2)));
   }
 }
@@ -0,0 +1,247 @@
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::process::ExitStatus;
+#[cfg(unix)]
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::process::{ChildStderr, ChildStdin, ChildStdout, Command};
+
+/// CPU time and peak memory of a finished command, including the descendants it waited for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+  pub cpu_ms: u128,
+  pub max_rss_kb: u64,
+}
+
+/// A running command, waited for together with its resource usage.
+///
+/// On unix the process is spawned through std and reaped only by [`Process::wait`]
+/// with `wait4`: tokio never holds the pid, so nothing can wait for it or signal it
+/// after it was reaped and the pid was reused. Like with `kill_on_drop`, a process
+/// that is dropped before it exits is killed.
+pub struct Process {
+  pub stdin: Option<ChildStdin>,
+  pub stdout: Option<ChildStdout>,
+  pub stderr: Option<ChildStderr>,
+  inner: Inner,
+}
+
+#[cfg(unix)]
+struct Inner {
+  pid: libc::pid_t,
+  // Выставляется под блокировкой перед тем, как процесс будет собран
+  exited: Arc<Mutex<bool>>,
+  // Ожидание запущено, дальше процесс соберет его поток
+  waiting: bool,
+}
+
+#[cfg(not(unix))]
+struct Inner {
+  child: tokio::process::Child,
+}
+
+impl Process {
+  #[cfg(unix)]
+  pub fn spawn(command: &mut Command) -> io::Result<Self> {
+    let mut child = command.as_std_mut().spawn()?;
+    let mut process = Self {
+      stdin: None,
+      stdout: None,
+      stderr: None,
+      inner: Inner {
+        pid: child.id() as libc::pid_t,
+        exited: Arc::new(Mutex::new(false)),
+        waiting: false,
+      },
+    };
+
+    // При ошибке здесь процесс убьет и соберет drop
+    process.stdin = child.stdin.take().map(ChildStdin::from_std).transpose()?;
+    process.stdout = child.stdout.take().map(ChildStdout::from_std).transpose()?;
+    process.stderr = child.stderr.take().map(ChildStderr::from_std).transpose()?;
+
+    Ok(process)
+  }
+
+  #[cfg(not(unix))]
+  pub fn spawn(command: &mut Command) -> io::Result<Self> {
+    let mut child = command.kill_on_drop(true).spawn()?;
+
+    Ok(Self {
+      stdin: child.stdin.take(),
+      stdout: child.stdout.take(),
+      stderr: child.stderr.take(),
+      inner: Inner { child },
+    })
+  }
+
+  #[cfg(unix)]
+  pub fn id(&self) -> Option<u32> {
+    Some(self.inner.pid as u32)
+  }
+
+  #[cfg(not(unix))]
+  pub fn id(&self) -> Option<u32> {
+    self.inner.child.id()
+  }
+
+  /// Waits for the command and collects its resource usage where the platform reports it.
+  #[cfg(unix)]
+  pub async fn wait(&mut self) -> io::Result<(ExitStatus, Option<ResourceUsage>)> {
+    use std::os::unix::process::ExitStatusExt;
+
+    self.inner.waiting = true;
+    let pid = self.inner.pid;
+    let exited = self.inner.exited.clone();
+
+    // Ожидание блокирующее, поэтому в отдельном потоке; он же соберет процесс,
+    // даже если это ожидание прервут
+    let (status, usage) = tokio::task::spawn_blocking(move || reap(pid, &exited))
+      .await
+      .map_err(io::Error::other)??;
+
+    Ok((ExitStatus::from_raw(status), Some(usage)))
+  }
+
+  #[cfg(not(unix))]
+  pub async fn wait(&mut self) -> io::Result<(ExitStatus, Option<ResourceUsage>)> {
+    self.inner.child.wait().await.map(|status| (status, None))
+  }
+}
+
+#[cfg(unix)]
+impl Drop for Process {
+  fn drop(&mut self) {
+    let pid = self.inner.pid;
+    let exited = self
+      .inner
+      .exited
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner);
+
+    // Пока флаг не выставлен, процесс не собран и pid все еще его
+    if !*exited {
+      unsafe {
+        libc::kill(pid, libc::SIGKILL);
+      }
+    }
+
+    drop(exited);
+
+    if !self.inner.waiting {
+      let exited = self.inner.exited.clone();
+      std::thread::spawn(move || reap(pid, &exited));
+    }
+  }
+}
+
+/// Waits until `pid` exits, then reaps it with `wait4` for its status and usage.
+///
+/// The exit is awaited without reaping first, so `exited` is set while the pid
+/// still belongs to the process.
+#[cfg(unix)]
+fn reap(pid: libc::pid_t, exited: &Mutex<bool>) -> io::Result<(i32, ResourceUsage)> {
+  loop {
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+    let result = unsafe {
+      libc::waitid(
+        libc::P_PID,
+        pid as libc::id_t,
+        &raw mut info,
+        libc::WEXITED | libc::WNOWAIT,
+      )
+    };
+
+    if result == 0 {
+      break;
+    }
+
+    let err = io::Error::last_os_error();
+    if err.kind() != io::ErrorKind::Interrupted {
+      return Err(err);
+    }
+  }
+
+  let mut exited = exited.lock().unwrap_or_else(PoisonError::into_inner);
+  *exited = true;
+
+  wait4(pid)
+}
+
+#[cfg(unix)]
+fn wait4(pid: libc::pid_t) -> io::Result<(i32, ResourceUsage)> {
+  let mut status = 0;
+  let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+
+  loop {
+    let result = unsafe { libc::wait4(pid, &raw mut status, 0, &raw mut rusage) };
+
+    if result == pid {
+      break;
+    }
+
+    let err = io::Error::last_os_error();
+    if err.kind() != io::ErrorKind::Interrupted {
+      return Err(err);
+    }
+  }
+
+  let cpu_ms = timeval_ms(rusage.ru_utime) + timeval_ms(rusage.ru_stime);
+  // На macOS ru_maxrss в байтах, на Linux и BSD в килобайтах
+  let max_rss_kb = if cfg!(target_os = "macos") {
+    rusage.ru_maxrss as u64 / 1024
+  } else {
+    rusage.ru_maxrss as u64
+  };
+
+  Ok((status, ResourceUsage { cpu_ms, max_rss_kb }))
+}
+
+#[cfg(unix)]
+fn timeval_ms(time: libc::timeval) -> u128 {
+  time.tv_sec as u128 * 1000 + time.tv_usec as u128 / 1000
+}
+
+/// CPU time used so far by the live processes of a process group.
+///
+/// Every process adds the time of the children it already waited for, so
+/// finished `sh -c` pipelines still count. Only Linux exposes it, via `/proc`.
+#[cfg(target_os = "linux")]
+pub fn process_group_cpu_time(pgid: u32) -> Option<Duration> {
+  let ticks_per_sec = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+  if ticks_per_sec <= 0 {
+    return None;
+  }
+
+  let mut ticks: u64 = 0;
+
+  for entry in std::fs::read_dir("/proc").ok()?.flatten() {
+    let Ok(stat) = std::fs::read_to_string(entry.path().join("stat")) else {
+      continue;
+    };
+    // Имя процесса в скобках может содержать пробелы, поля идут после последней `)`
+    let Some((_, fields)) = stat.rsplit_once(')') else {
+      continue;
+    };
+    let fields: Vec<&str> = fields.split_whitespace().collect();
+
+    // После имени: state(0) ppid(1) pgrp(2) ... utime(11) stime(12) cutime(13) cstime(14)
+    if fields.get(2).and_then(|pgrp| pgrp.parse::<u32>().ok()) != Some(pgid) {
+      continue;
+    }
+
+    ticks += fields
+      .iter()
+      .skip(11)
+      .take(4)
+      .filter_map(|value| value.parse::<u64>().ok())
+      .sum::<u64>();
+  }
+
+  Some(Duration::from_millis(ticks * 1000 / ticks_per_sec as u64))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn process_group_cpu_time(_pgid: u32) -> Option<Duration> {
+  None
+}
@@ -0,0 +1,233 @@
+use serde_json::{Map, Value};
+
+/// Top-level options; any other key is a group.
+const TOP_LEVEL_KEYS: &[&str] = &[
+  "timeout",
+  "kill_grace",
+  "output_limit_kb",
+  "nice",
+  "command_prefix",
+  "execution_order",
+  "strict_coverage",
+  "strict_patterns",
+  "skip_ignored",
+  "renames",
+  "annotation_pattern",
+  "html_report",
+  "wait_on_exit",
+  "linger_ms",
+  "max_time",
+  "redraw_interval_ms",
+  "task_sort",
+  "group_by",
+  "stats_sort",
+  "stats_order",
+  "on_empty",
+  "show_stats",
+  "on_missing_command",
+  "fail_on_empty_commit",
+  "symbols",
+  "overrides",
+  "commands",
+  "$schema",
+];
+
+const GROUP_KEYS: &[&str] = &[
+  "timeout",
+  "kill_grace",
+  "execution_order",
+  "mutex",
+  "only_changed_lines",
+  "env",
+  "clean_env",
+  "warning_pattern",
+  "fail_on_output",
+  "filter_output",
+  "capture",
+  "stop_on_error",
+  "group_fail_fast",
+  "output_limit_kb",
+  "priority",
+  "scope",
+  "binary",
+  "tty",
+  "absolute_paths",
+  "retry_on",
+  "retries",
+  "timeout_clock",
+  "max_failures",
+  "fail_on_changes",
+  "nice",
+  "command_prefix",
+  "when",
+  "patterns",
+];
+
+const DURATION_KEYS: &[&str] = &["timeout", "kill_grace", "max_time"];
+
+/// Allowed values of enum options, shared by the top level and groups.
+pub(crate) const ENUM_VALUES: &[(&str, &[&str])] = &[
+  ("execution_order", &["parallel", "sequential"]),
+  ("wait_on_exit", &["off", "timed", "manual"]),
+  ("on_empty", &["silent", "message", "error"]),
+  ("on_missing_command", &["error", "skip", "warn"]),
+  ("task_sort", &["status", "duration", "file", "none"]),
+  ("group_by", &["group", "file"]),
+  ("stats_sort", &["name", "total", "avg", "count"]),
+  ("stats_order", &["asc", "desc"]),
+  ("scope", &["file", "group"]),
+  ("retry_on", &["never", "timeout", "failure", "any"]),
+  ("timeout_clock", &["wall", "cpu"]),
+  ("renames", &["new", "old", "both"]),
+];
+
+const MODE_KEYS: &[&str] = &["fix", "check"];
+
+/// Checks a raw config before it is deserialized.
+///
+/// Serde reports a typo in a flattened group as "data did not match any variant",
+/// so the common mistakes are caught here with the exact location and a suggestion.
+pub fn check_config(config: &Value) -> Result<(), String> {
+  let Some(config) = config.as_object() else {
+    return Err("expected a table of options and groups".to_string());
+  };
+
+  for (key, value) in config {
+    if TOP_LEVEL_KEYS.contains(&key.as_str()) {
+      check_option(key, value).map_err(|details| format!("option '{}': {}", key, details))?;
+      continue;
+    }
+
+    // Все, что не опция верхнего уровня и не таблица, скорее всего опечатка в имени опции
+    let Some(group) = value.as_object() else {
+      return Err(format!(
+        "unknown option '{}'{}",
+        key,
+        suggestion(key, TOP_LEVEL_KEYS)
+      ));
+    };
+
+    check_group(group).map_err(|details| format!("group '{}': {}", key, details))?;
+  }
+
+  Ok(())
+}
+
+fn check_group(group: &Map<String, Value>) -> Result<(), String> {
+  for (key, value) in group {
+    if !GROUP_KEYS.contains(&key.as_str()) {
+      return Err(format!(
+        "unknown option '{}'{}",
+        key,
+        suggestion(key, GROUP_KEYS)
+      ));
+    }
+
+    check_option(key, value).map_err(|details| format!("option '{}': {}", key, details))?;
+  }
+
+  let Some(patterns) = group.get("patterns") else {
+    return Err("missing 'patterns' table".to_string());
+  };
+
+  let Some(patterns) = patterns.as_object() else {
+    return Err("'patterns' must be a table of glob patterns to commands".to_string());
+  };
+
+  for (pattern, commands) in patterns {
+    check_commands(commands).map_err(|details| format!("pattern '{}': {}", pattern, details))?;
+  }
+
+  Ok(())
+}
+
+fn check_option(key: &str, value: &Value) -> Result<(), String> {
+  if let Some((_, allowed)) = ENUM_VALUES.iter().find(|(name, _)| *name == key) {
+    let Some(value) = value.as_str() else {
+      return Err(format!("expected one of {}", allowed.join(", ")));
+    };
+
+    if !allowed.contains(&value) {
+      return Err(format!(
+        "unknown value '{}'{}",
+        value,
+        suggestion(value, allowed)
+      ));
+    }
+  }
+
+  if DURATION_KEYS.contains(&key)
+    && let Some(value) = value.as_str()
+    && parse_duration::parse(value).is_err()
+  {
+    return Err(format!(
+      "'{}' is not a duration, use values like \"30s\" or \"2m\"",
+      value
+    ));
+  }
+
+  Ok(())
+}
+
+/// A pattern needs at least one command, either as a list or in its `fix`/`check` lists.
+fn check_commands(commands: &Value) -> Result<(), String> {
+  match commands {
+    Value::Array(list) if list.is_empty() => Err("no commands".to_string()),
+    Value::Array(_) => Ok(()),
+    Value::Object(modes) => {
+      if let Some(key) = modes.keys().find(|key| !MODE_KEYS.contains(&key.as_str())) {
+        return Err(format!(
+          "unknown mode '{}'{}",
+          key,
+          suggestion(key, MODE_KEYS)
+        ));
+      }
+
+      let has_commands = modes
+        .values()
+        .any(|list| list.as_array().is_some_and(|list| !list.is_empty()));
+
+      if has_commands {
+        Ok(())
+      } else {
+        Err("no commands in 'fix' or 'check'".to_string())
+      }
+    }
+    _ => Err("expected a list of commands".to_string()),
+  }
+}
+
+/// Formats a "did you mean" hint for the closest candidate, or lists all of them.
+fn suggestion(value: &str, candidates: &[&str]) -> String {
+  let closest = candidates
+    .iter()
+    .map(|candidate| (candidate, edit_distance(value, candidate)))
+    .min_by_key(|(_, distance)| *distance);
+
+  match closest {
+    Some((candidate, distance)) if distance <= 3 => format!(", did you mean '{}'?", candidate),
+    _ => format!(", expected one of {}", candidates.join(", ")),
+  }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+  let b: Vec<char> = b.chars().collect();
+  let mut row: Vec<usize> = (0..=b.len()).collect();
+
+  for (i, ca) in a.chars().enumerate() {
+    let mut diagonal = row[0];
+    row[0] = i + 1;
+
+    for (j, cb) in b.iter().enumerate() {
+      let above = row[j + 1];
+      row[j + 1] = if ca == *cb {
+        diagonal
+      } else {
+        1 + diagonal.min(above).min(row[j])
+      };
+      diagonal = above;
+    }
+  }
+
+  row[b.len()]
+}
@@ -1,9 +1,146 @@
 #![deny(clippy::all)]
 
-use fast_staged::run;
+use fast_staged::report::{RunReport, RunSummary, TaskReport};
+use fast_staged::{config_formats, run, run_headless, run_interactive, REPORTERS, VERSION};
 use napi::bindgen_prelude::*;
+use napi_derive::napi;
 use tokio;
 
+/// What the native module supports, so the JS side can degrade gracefully.
+#[napi(object)]
+pub struct Capabilities {
+  pub version: String,
+  pub reporters: Vec<String>,
+  pub config_formats: Vec<String>,
+}
+
+#[napi]
+pub fn capabilities() -> Capabilities {
+  Capabilities {
+    version: VERSION.to_string(),
+    reporters: REPORTERS.iter().map(|s| s.to_string()).collect(),
+    config_formats: config_formats().into_iter().map(String::from).collect(),
+  }
+}
+
+/// Result of a single task as seen from JS.
+#[napi(object)]
+pub struct TaskResult {
+  pub filename: String,
+  pub command: String,
+  pub group: String,
+  pub status: String,
+  pub error: Option<String>,
+  pub duration_ms: f64,
+  pub started_at_ms: Option<f64>,
+  pub finished_at_ms: Option<f64>,
+}
+
+impl From<TaskReport> for TaskResult {
+  fn from(report: TaskReport) -> Self {
+    Self {
+      filename: report.filename,
+      command: report.command,
+      group: report.group,
+      status: report.status,
+      error: report.error,
+      duration_ms: report.duration_ms as f64,
+      started_at_ms: report.started_at_ms.map(|ms| ms as f64),
+      finished_at_ms: report.finished_at_ms.map(|ms| ms as f64),
+    }
+  }
+}
+
+/// Totals of a run as seen from JS.
+#[napi(object)]
+pub struct RunSummaryResult {
+  pub total: u32,
+  pub passed: u32,
+  pub failed: u32,
+  pub timed_out: u32,
+  /// Skipped or cancelled tasks.
+  pub skipped: u32,
+  pub wall_ms: f64,
+}
+
+impl From<RunSummary> for RunSummaryResult {
+  fn from(summary: RunSummary) -> Self {
+    Self {
+      total: summary.total as u32,
+      passed: summary.passed as u32,
+      failed: summary.failed as u32,
+      timed_out: summary.timed_out as u32,
+      skipped: summary.skipped as u32,
+      wall_ms: summary.wall_ms as f64,
+    }
+  }
+}
+
+/// Results of a run: every task and their totals.
+#[napi(object)]
+pub struct RunResult {
+  pub tasks: Vec<TaskResult>,
+  pub summary: RunSummaryResult,
+}
+
+impl From<RunReport> for RunResult {
+  fn from(report: RunReport) -> Self {
+    Self {
+      tasks: report.tasks.into_iter().map(TaskResult::from).collect(),
+      summary: report.summary.into(),
+    }
+  }
+}
+
+/// Runs the staged tasks without a TUI and resolves with their results and totals.
+///
+/// Errors are thrown as JS errors; the Node process is never exited.
+#[napi]
+pub async fn run_tasks(args: Option<Vec<String>>) -> Result<RunResult> {
+  let report = run_headless(args.unwrap_or_default())
+    .await
+    .map_err(|e| Error::from_reason(e.to_string()))?;
+
+  Ok(report.into())
+}
+
+/// Options for [`run_tui`].
+#[napi(object)]
+pub struct RunOptions {
+  /// How long the final frame stays on screen before the promise resolves.
+  ///
+  /// Setting it closes the TUI on its own instead of waiting for a key press.
+  pub linger_ms: Option<u32>,
+}
+
+/// Runs the staged tasks with the TUI and resolves with their results and totals
+/// once it closes.
+///
+/// Unlike the CLI the Node process is never exited, so the caller decides the
+/// exit code and prints its own messages.
+#[napi]
+pub async fn run_tui(args: Option<Vec<String>>, options: Option<RunOptions>) -> Result<RunResult> {
+  let mut argv = Vec::new();
+
+  if let Some(linger_ms) = options.and_then(|options| options.linger_ms) {
+    // Перед пользовательскими аргументами, чтобы не попасть после `--`
+    argv.extend([
+      "--wait-on-exit".to_string(),
+      "timed".to_string(),
+      "--linger-ms".to_string(),
+      linger_ms.to_string(),
+    ]);
+  }
+
+  argv.extend(args.unwrap_or_default());
+
+  let report = run_interactive(argv)
+    .await
+    .map_err(|e| Error::from_reason(e.to_string()))?;
+
+  Ok(report.into())
+}
+
 #[napi_derive::module_init]
 fn init() {
   let rt = tokio::runtime::Builder::new_multi_thread()